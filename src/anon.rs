@@ -1,74 +1,461 @@
-use super::{common::*, *};
+use {
+    super::{common::*, *},
+    syn::ext::IdentExt,
+};
 
 pub fn anon(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let anon = parse_macro_input!(tokens as Anon);
-    anon.expand().into()
+    let anon = parse_macro_input!(tokens as AnonInput);
+    match anon {
+        AnonInput::Anon(anon) => anon.expand(),
+        AnonInput::Struct(from_struct) => from_struct.expand(),
+    }
+    .into()
+}
+
+enum AnonInput {
+    Anon(Box<Anon>),
+    Struct(Box<FromStruct>),
+}
+
+impl Parse for AnonInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![..]) {
+            input
+                .parse()
+                .map(|from_struct| AnonInput::Struct(Box::new(from_struct)))
+        } else {
+            input.parse().map(|anon| AnonInput::Anon(Box::new(anon)))
+        }
+    }
+}
+
+// `..source`, `..+source`, `..&source` or `..&mut source`, with all the fields of a struct
+// deriving `AnonFields`
+struct FromStruct {
+    dotdot: Token![..],
+    modifier: Option<SpreadModifier>,
+    source: syn::Expr,
+}
+
+impl FromStruct {
+    fn expand(self) -> TokenStream {
+        let Self {
+            dotdot,
+            modifier,
+            source,
+        } = self;
+
+        // A missing derive is reported on `..`
+        let method = |name: &str| {
+            let method = syn::Ident::new(name, dotdot.span());
+            quote_spanned! { dotdot.span()=> . #method () }
+        };
+        let methods = match modifier {
+            None => method("__spread_macros_anon"),
+            Some(SpreadModifier::Ref(_)) => method("__spread_macros_anon_ref"),
+            Some(SpreadModifier::RefMut(_, _)) => method("__spread_macros_anon_mut"),
+            _ => {
+                let by_ref = method("__spread_macros_anon_ref");
+                let cloned = method("__spread_macros_cloned");
+                quote! { #by_ref #cloned }
+            }
+        };
+
+        quote! { (#source) #methods }
+    }
+}
+
+impl Parse for FromStruct {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let dotdot = input.parse()?;
+
+        let modifier = if input.peek(Token![+]) {
+            Some(SpreadModifier::Clone(input.parse()?))
+        } else if input.peek(Token![&]) && input.peek2(Token![mut]) {
+            Some(SpreadModifier::RefMut(input.parse()?, input.parse()?))
+        } else if input.peek(Token![&]) {
+            Some(SpreadModifier::Ref(input.parse()?))
+        } else {
+            None
+        };
+
+        let source = input.parse()?;
+
+        if !input.is_empty() {
+            let _: Option<Token![,]> = input.parse()?;
+        }
+        if !input.is_empty() {
+            return Err(syn::Error::new(
+                input.span(),
+                "`..source` must be the only content of `anon!`",
+            ));
+        }
+
+        Ok(Self {
+            dotdot,
+            modifier,
+            source,
+        })
+    }
 }
 
 pub struct Anon {
     pub attrs: Vec<syn::Attribute>,
+    // Some = `#![derive_only(..)]`, replaces the default derives
+    pub derives: Option<Punctuated<syn::Path, Token![,]>>,
+    // `#![impl { .. }]`, methods implemented on the struct
+    pub methods: Vec<syn::ImplItem>,
+    // `#![as trait Path { .. }]`, traits whose methods return the field of the same name
+    pub traits: Vec<(syn::Path, Vec<syn::Signature>)>,
+    // Some = `#![display]`, with an optional format string `#![display = ".."]`
+    pub display: Option<Option<syn::LitStr>>,
+    // Some = `#![fields]`, generates a `fields` method iterating over names and values
+    pub fields: Option<syn::Attribute>,
+    // Some = `#![serde]` or `#![serde(..)]`, path to the serde crate used by the derives
+    pub serde: Option<syn::Path>,
+    // `#![ord(..)]`, fields compared first by `PartialOrd` and `Ord`
+    pub ord: Option<Punctuated<syn::Ident, Token![,]>>,
+    // Some = `#![into(Target)]`, value is converted into a struct with the same fields
+    pub into: Option<syn::Path>,
+    // Some = `#![name = ".."]`, name of the anonymous struct instead of `Anon`
+    pub name: Option<syn::Ident>,
+    // Some = `vis struct Name { .. }`, emits a named struct at item scope
+    pub item: Option<(syn::Visibility, syn::Ident)>,
+    // Outer attributes (such as doc comments) of each field in item mode
+    pub fields_attrs: Vec<Vec<syn::Attribute>>,
     pub items: Punctuated<SpreadItem, Token![,]>,
 }
 
 impl Anon {
     pub fn expand(self) -> TokenStream {
-        let Self { mut attrs, items } = self;
+        let Self {
+            mut attrs,
+            derives,
+            methods,
+            traits,
+            display,
+            fields,
+            serde,
+            ord,
+            into,
+            name,
+            item,
+            fields_attrs,
+            items,
+        } = self;
 
         // Transforn inner attributes `#![...]` into outer attributes `#[...]`
         for attr in &mut attrs {
             attr.style = syn::AttrStyle::Outer;
         }
 
-        let let_sources = items.iter().filter_map(|item| match item {
-            SpreadItem::SpreadList(SpreadList {
-                source,
-                source_ident,
-                ..
-            }) => Some(quote! { let #source_ident = #source; }),
-            _ => None,
+        let derives = match (derives, &item) {
+            (Some(derives), _) => quote! { #[derive(#derives)] },
+            (None, Some(_)) => quote! { #[derive(Clone, Debug, PartialEq, Eq)] },
+            (None, None) => quote! { #[derive(Copy, Clone, Debug, PartialEq, Eq)] },
+        };
+
+        let serde = serde.map(|serde| {
+            quote! { #[derive(#serde::Serialize, #serde::Deserialize)] }
         });
 
-        let fields_expansions = items.iter().map(SpreadItem::field_expansion);
+        let is_item = item.is_some();
+        let (vis, struct_name) = match item {
+            Some((vis, name)) => (vis, name),
+            None => (
+                syn::Visibility::Inherited,
+                name.unwrap_or_else(|| syn::Ident::new("Anon", Span::call_site())),
+            ),
+        };
 
         let mut fields_name = vec![];
+        let mut fields_type = vec![];
+        let mut generics = vec![];
+
+        // Fields with a provided type use it, others are generic
+        let mut push_field = |field: &Field| {
+            fields_name.push(field.name.clone());
+            match &field.ty {
+                Some(ty) => fields_type.push(quote! { #ty }),
+                None => {
+                    let generic =
+                        syn::Ident::new(&format!("T{}", generics.len()), Span::call_site());
+                    fields_type.push(quote! { #generic });
+                    generics.push(generic);
+                }
+            }
+        };
 
         for item in items.iter() {
             match item {
-                SpreadItem::Field(Field { name, .. }) => {
-                    fields_name.push(name.clone());
-                }
+                SpreadItem::Field(field) => push_field(field),
                 SpreadItem::SpreadList(SpreadList { fields_list, .. }) => {
-                    for Field { name, .. } in fields_list.iter() {
-                        fields_name.push(name.clone());
-                    }
+                    fields_list.iter().for_each(&mut push_field);
                 }
-                SpreadItem::FinalSpread(_, _) => {
+                SpreadItem::FinalSpread(_, _, _) => {
                     unreachable!("FinalSpread is not allowed in anon!")
                 }
             }
         }
 
-        let fields_type: Vec<_> = fields_name
-            .iter()
-            .enumerate()
-            .map(|(i, _)| syn::Ident::new(&format!("T{i}"), Span::call_site()))
+        let methods = if methods.is_empty() {
+            None
+        } else {
+            Some(quote! {
+                impl < #( #generics ),* > #struct_name < #( #generics ),* > {
+                    #( #methods )*
+                }
+            })
+        };
+
+        let mut traits_impls = vec![];
+        for (trait_path, signatures) in traits {
+            let mut bounds = vec![];
+            let mut bodies = vec![];
+
+            for sig in &signatures {
+                let Some(i) = fields_name.iter().position(|field| *field == sig.ident) else {
+                    return syn::Error::new(sig.ident.span(), "unknown field").into_compile_error();
+                };
+                let field_name = &fields_name[i];
+                let field_type = &fields_type[i];
+
+                let is_getter = sig.generics.params.is_empty()
+                    && sig.inputs.len() == 1
+                    && matches!(
+                        sig.inputs.first(),
+                        Some(syn::FnArg::Receiver(syn::Receiver {
+                            reference: Some(_),
+                            mutability: None,
+                            ..
+                        }))
+                    );
+
+                let ret = match &sig.output {
+                    syn::ReturnType::Type(_, ret) if is_getter => ret,
+                    _ => {
+                        return syn::Error::new(
+                            sig.span(),
+                            "expected a getter `fn field(&self) -> Type`",
+                        )
+                        .into_compile_error()
+                    }
+                };
+
+                // References are borrowed from the field, other types are cloned and converted
+                let body = match &**ret {
+                    syn::Type::Reference(syn::TypeReference {
+                        mutability: None,
+                        elem,
+                        ..
+                    }) => {
+                        bounds.push(quote! { #field_type: ::core::borrow::Borrow<#elem> });
+                        quote! { ::core::borrow::Borrow::borrow(&self.#field_name) }
+                    }
+                    _ => {
+                        bounds.push(quote! {
+                            #field_type: ::core::clone::Clone + ::core::convert::Into<#ret>
+                        });
+                        quote! {
+                            ::core::convert::Into::into(::core::clone::Clone::clone(&self.#field_name))
+                        }
+                    }
+                };
+
+                bodies.push(quote! { #sig { #body } });
+            }
+
+            traits_impls.push(quote! {
+                impl < #( #generics ),* > #trait_path for #struct_name < #( #generics ),* >
+                where #( #bounds ),* {
+                    #( #bodies )*
+                }
+            });
+        }
+
+        let fields = fields.map(|_| {
+            let names = fields_name.iter().map(|name| name.unraw().to_string());
+
+            quote! {
+                impl < #( #generics: ::core::fmt::Debug ),* > #struct_name < #( #generics ),* > {
+                    pub fn fields(&self) -> impl ::core::iter::Iterator<
+                        Item = (&'static str, &dyn ::core::fmt::Debug)
+                    > + '_ {
+                        ::core::iter::IntoIterator::into_iter([
+                            #( (#names, &self.#fields_name as &dyn ::core::fmt::Debug) ),*
+                        ])
+                    }
+                }
+            }
+        });
+
+        let display = display.map(|fmt| {
+            let bound = fmt.is_none().then(|| quote! { : ::core::fmt::Display });
+            let where_clause = fmt.as_ref().map(|fmt| {
+                let bounds = format_bounds(fmt, &fields_name, &fields_type);
+                quote! { where #( #bounds ),* }
+            });
+            let body = match fmt {
+                Some(fmt) => quote! {
+                    #[allow(unused_variables)]
+                    let Self { #( #fields_name ),* } = self;
+                    ::core::write!(f, #fmt)
+                },
+                None => {
+                    let fields_fmt = fields_name
+                        .iter()
+                        .map(|name| format!("{} = {{}}", name.unraw()))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    quote! {
+                        ::core::write!(f, #fields_fmt, #( self.#fields_name ),*)
+                    }
+                }
+            };
+
+            quote! {
+                impl < #( #generics #bound ),* > ::core::fmt::Display
+                for #struct_name < #( #generics ),* > #where_clause {
+                    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                        #body
+                    }
+                }
+            }
+        });
+
+        let ord = match ord {
+            Some(ord) => {
+                for name in ord.iter() {
+                    if !fields_name.contains(name) {
+                        return syn::Error::new(name.span(), "unknown field").into_compile_error();
+                    }
+                }
+
+                // Listed fields are compared first, then the other ones in declaration order to
+                // stay consistent with `Eq`
+                let ord_names = ord.iter().chain(
+                    fields_name
+                        .iter()
+                        .filter(|field| !ord.iter().any(|name| name == *field)),
+                );
+
+                Some(quote! {
+                    impl < #( #generics ),* > ::core::cmp::PartialOrd
+                    for #struct_name < #( #generics ),* >
+                    where Self: ::core::cmp::Ord {
+                        fn partial_cmp(&self, other: &Self)
+                            -> ::core::option::Option<::core::cmp::Ordering> {
+                            ::core::option::Option::Some(::core::cmp::Ord::cmp(self, other))
+                        }
+                    }
+
+                    impl < #( #generics ),* > ::core::cmp::Ord
+                    for #struct_name < #( #generics ),* >
+                    where Self: ::core::cmp::Eq, #( #fields_type: ::core::cmp::Ord ),* {
+                        fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+                            ::core::cmp::Ordering::Equal
+                            #(
+                                .then_with(|| ::core::cmp::Ord::cmp(
+                                    &self.#ord_names,
+                                    &other.#ord_names,
+                                ))
+                            )*
+                        }
+                    }
+                })
+            }
+            None => None,
+        };
+
+        let fields_attrs: Vec<_> = (0..fields_name.len())
+            .map(|i| {
+                let attrs = fields_attrs.get(i).map(Vec::as_slice).unwrap_or_default();
+                quote! { #( #attrs )* }
+            })
             .collect();
 
+        let definition = quote! {
+            #derives
+            #serde
+            #(#attrs)*
+            #vis struct #struct_name < #( #generics ),* > {
+                #(
+                    #fields_attrs
+                    #vis #fields_name: #fields_type
+                ),*
+            }
+
+            #methods
+
+            #( #traits_impls )*
+
+            #display
+
+            #fields
+
+            #ord
+        };
+
+        let fields_expansions = items.iter().map(SpreadItem::field_expansion);
+
+        // Item mode: every field has a type and a value, checked while parsing
+        if is_item {
+            // `#![into(Target)]` implements the conversion, as field types are known
+            let into = into.map(|target| {
+                quote! {
+                    impl ::core::convert::From<#struct_name> for #target {
+                        fn from(value: #struct_name) -> Self {
+                            let #struct_name { #( #fields_name ),* } = value;
+                            Self { #( #fields_name ),* }
+                        }
+                    }
+                }
+            });
+
+            return quote! {
+                #definition
+
+                #into
+
+                impl ::core::default::Default for #struct_name {
+                    fn default() -> Self {
+                        Self {
+                            #( #fields_expansions ),*
+                        }
+                    }
+                }
+            };
+        }
+
+        let let_sources = items.iter().filter_map(|item| match item {
+            SpreadItem::SpreadList(spread_list) => Some(spread_list.source_binding()),
+            _ => None,
+        });
+
+        let value = quote! {
+            #struct_name {
+                #( #fields_expansions ),*
+            }
+        };
+
+        // `#![into(Target)]` moves the fields into the target struct
+        let value = match into {
+            Some(target) => quote! {
+                {
+                    let #struct_name { #( #fields_name ),* } = #value;
+                    #target { #( #fields_name ),* }
+                }
+            },
+            None => value,
+        };
+
         quote! {
             {
-                #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-                #(#attrs)*
-                struct Anon < #( #fields_type ),* > {
-                    #(
-                        #fields_name: #fields_type
-                    ),*
-                }
+                #definition
 
                 #( #let_sources )*
 
-                Anon {
-                    #( #fields_expansions ),*
-                }
+                #value
             }
         }
     }
@@ -76,9 +463,198 @@ impl Anon {
 
 impl Parse for Anon {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let attrs = input.call(syn::Attribute::parse_inner)?;
+        let mut attrs = vec![];
+        let mut derives = None;
+        let mut methods = vec![];
+        let mut traits = vec![];
+        let mut display = None;
+        let mut ord = None;
+        let mut into = None;
+        let mut serde = None;
+        let mut fields = None;
+        let mut name: Option<syn::Ident> = None;
+        let mut by_ref = None;
+
+        // Inner attributes are parsed manually as some options start with a keyword, which is
+        // not a valid attribute path.
+        while input.peek(Token![#]) && input.peek2(Token![!]) {
+            let pound_token = input.parse()?;
+            let bang: Token![!] = input.parse()?;
+            let bracketed;
+            let bracket_token = syn::bracketed!(bracketed in input);
+
+            if bracketed.peek(Token![impl]) {
+                let _: Token![impl] = bracketed.parse()?;
+                let braced;
+                braced!(braced in bracketed);
+                while !braced.is_empty() {
+                    methods.push(braced.parse()?);
+                }
+                continue;
+            }
+
+            if bracketed.peek(Token![as]) && bracketed.peek2(Token![trait]) {
+                let _: Token![as] = bracketed.parse()?;
+                let _: Token![trait] = bracketed.parse()?;
+                let trait_path = bracketed.parse()?;
+                let braced;
+                braced!(braced in bracketed);
+                let mut signatures = vec![];
+                while !braced.is_empty() {
+                    let item: syn::TraitItemFn = braced.parse()?;
+                    if let Some(block) = item.default {
+                        return Err(syn::Error::new(
+                            block.span(),
+                            "methods are implemented by returning the field of the same name",
+                        ));
+                    }
+                    signatures.push(item.sig);
+                }
+                traits.push((trait_path, signatures));
+                continue;
+            }
+
+            let attr = syn::Attribute {
+                pound_token,
+                style: syn::AttrStyle::Inner(bang),
+                bracket_token,
+                meta: bracketed.parse()?,
+            };
+
+            if attr.path().is_ident("derive_only") {
+                derives = Some(attr.parse_args_with(Punctuated::parse_terminated)?);
+            } else if attr.path().is_ident("ord") {
+                ord = Some(attr.parse_args_with(Punctuated::parse_terminated)?);
+            } else if attr.path().is_ident("fields") {
+                attr.meta.require_path_only()?;
+                fields = Some(attr);
+            } else if attr.path().is_ident("serde") {
+                // Other serde options are kept as a container attribute
+                serde = Some(syn::parse_quote!(::serde));
+                if let syn::Meta::List(list) = &attr.meta {
+                    list.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("crate") {
+                            let path: syn::LitStr = meta.value()?.parse()?;
+                            serde = Some(path.parse()?);
+                        } else if meta.input.peek(Token![=]) {
+                            let _: syn::Expr = meta.value()?.parse()?;
+                        } else if meta.input.peek(syn::token::Paren) {
+                            let _: proc_macro2::TokenTree = meta.input.parse()?;
+                        }
+                        Ok(())
+                    })?;
+                    attrs.push(attr);
+                }
+            } else if attr.path().is_ident("into") {
+                into = Some(attr.parse_args()?);
+            } else if attr.path().is_ident("by_ref") {
+                attr.meta.require_path_only()?;
+                by_ref = Some(attr);
+            } else if attr.path().is_ident("name") {
+                let syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(lit),
+                    ..
+                }) = &attr.meta.require_name_value()?.value
+                else {
+                    return Err(syn::Error::new(
+                        attr.span(),
+                        "expected `#![name = \"Name\"]`",
+                    ));
+                };
+                name = Some(lit.parse()?);
+            } else if attr.path().is_ident("display") {
+                display = Some(match &attr.meta {
+                    syn::Meta::Path(_) => None,
+                    syn::Meta::NameValue(syn::MetaNameValue {
+                        value:
+                            syn::Expr::Lit(syn::ExprLit {
+                                lit: syn::Lit::Str(fmt),
+                                ..
+                            }),
+                        ..
+                    }) => Some(fmt.clone()),
+                    _ => {
+                        return Err(syn::Error::new(
+                            attr.span(),
+                            "expected `#![display]` or `#![display = \"format string\"]`",
+                        ))
+                    }
+                });
+            } else {
+                attrs.push(attr);
+            }
+        }
+
+        if input.peek(Token![pub]) || input.peek(Token![struct]) {
+            if let Some(name) = name {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "`#![name = \"..\"]` can't be used when declaring a struct",
+                ));
+            }
+
+            if let Some(by_ref) = by_ref {
+                return Err(syn::Error::new(
+                    by_ref.span(),
+                    "`#![by_ref]` can't be used when declaring a struct",
+                ));
+            }
+
+            let vis = input.parse()?;
+            let _: Token![struct] = input.parse()?;
+            let name = input.parse()?;
+
+            let braced;
+            braced!(braced in input);
+            let mut items = Punctuated::<SpreadItem, Token![,]>::new();
+            let mut fields_attrs = vec![];
+            while !braced.is_empty() {
+                fields_attrs.push(braced.call(syn::Attribute::parse_outer)?);
+                items.push_value(braced.parse()?);
+                if braced.is_empty() {
+                    break;
+                }
+                items.push_punct(braced.parse()?);
+            }
+
+            for item in items.iter() {
+                let span = match item {
+                    SpreadItem::Field(Field {
+                        ty: Some(_),
+                        value: Some(_),
+                        optional: None,
+                        is_mut: None,
+                        ..
+                    }) => continue,
+                    SpreadItem::Field(field) => field.name.span(),
+                    SpreadItem::SpreadList(list) => list.source.span(),
+                    SpreadItem::FinalSpread(dotdot, _, _) => dotdot.span(),
+                };
 
-        let items = Punctuated::<SpreadItem, Token![,]>::parse_terminated(input)?;
+                return Err(syn::Error::new(
+                    span,
+                    "only `name: Type = value` fields are allowed when declaring a struct",
+                ));
+            }
+
+            return Ok(Self {
+                attrs,
+                derives,
+                methods,
+                traits,
+                display,
+                fields,
+                serde,
+                ord,
+                into,
+                name: None,
+                item: Some((vis, name)),
+                fields_attrs,
+                items,
+            });
+        }
+
+        let mut items = Punctuated::<SpreadItem, Token![,]>::parse_terminated(input)?;
 
         // Forbid empty struct
         if items.is_empty() {
@@ -90,10 +666,25 @@ impl Parse for Anon {
 
         // No `..remaining`
         for item in items.iter() {
-            if let SpreadItem::FinalSpread(dotdot, _) = item {
+            if let SpreadItem::FinalSpread(dotdot, _, _) = item {
                 return Err(syn::Error::new(
                     dotdot.span(),
-                    "`..remaining` is not allowed in this macro",
+                    "`..remaining` is not allowed in this macro, list the fields to copy with \
+                    `{ field1, field2 } in remaining`",
+                ));
+            }
+        }
+
+        // No `field ?: value`, which requires `..remaining`
+        for item in items.iter() {
+            if let SpreadItem::Field(Field {
+                optional: Some(token_optional),
+                ..
+            }) = item
+            {
+                return Err(syn::Error::new(
+                    token_optional.span(),
+                    "`field ?: value` is not allowed in this macro",
                 ));
             }
         }
@@ -124,6 +715,85 @@ impl Parse for Anon {
             }
         }
 
-        Ok(Self { attrs, items })
+        // `#![by_ref]` borrows fields that have neither a modifier nor a value
+        if let Some(by_ref) = by_ref {
+            let by_ref_field = |field: &mut Field| {
+                if field.modifier.is_none() && field.value.is_none() {
+                    field.modifier = Some(SpreadModifier::Ref(Token![&](by_ref.span())));
+                }
+            };
+
+            for item in items.iter_mut() {
+                match item {
+                    SpreadItem::Field(field) => by_ref_field(field),
+                    SpreadItem::SpreadList(list) => {
+                        list.fields_list.iter_mut().for_each(by_ref_field)
+                    }
+                    SpreadItem::FinalSpread(_, _, _) => (),
+                }
+            }
+        }
+
+        Ok(Self {
+            attrs,
+            derives,
+            methods,
+            traits,
+            display,
+            fields,
+            serde,
+            ord,
+            into,
+            name,
+            item: None,
+            fields_attrs: vec![],
+            items,
+        })
     }
 }
+
+// Bounds of the fields used by name in a `#![display = ".."]` format string, with the formatting
+// trait of their placeholder
+fn format_bounds(
+    fmt: &syn::LitStr,
+    fields_name: &[syn::Ident],
+    fields_type: &[TokenStream],
+) -> Vec<TokenStream> {
+    let fmt = fmt.value();
+    let mut bounds = vec![];
+    let mut chars = fmt.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '{' {
+            continue;
+        }
+        if chars.peek() == Some(&'{') {
+            chars.next();
+            continue;
+        }
+
+        let placeholder: String = chars.by_ref().take_while(|&c| c != '}').collect();
+        let (arg, spec) = placeholder.split_once(':').unwrap_or((&placeholder, ""));
+        let format_trait = match spec.chars().last() {
+            Some('?') => quote! { ::core::fmt::Debug },
+            Some('x') => quote! { ::core::fmt::LowerHex },
+            Some('X') => quote! { ::core::fmt::UpperHex },
+            Some('o') => quote! { ::core::fmt::Octal },
+            Some('b') => quote! { ::core::fmt::Binary },
+            Some('e') => quote! { ::core::fmt::LowerExp },
+            Some('E') => quote! { ::core::fmt::UpperExp },
+            Some('p') => quote! { ::core::fmt::Pointer },
+            _ => quote! { ::core::fmt::Display },
+        };
+
+        if let Some(i) = fields_name
+            .iter()
+            .position(|name| name.unraw() == arg.trim())
+        {
+            let ty = &fields_type[i];
+            bounds.push(quote! { #ty: #format_trait });
+        }
+    }
+
+    bounds
+}
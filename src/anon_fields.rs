@@ -0,0 +1,109 @@
+use super::*;
+
+pub fn anon_fields(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(tokens as syn::DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+// Name of the struct generated by the derive, built by `anon!(..source)`
+fn anon_struct_name(name: &syn::Ident) -> syn::Ident {
+    syn::Ident::new(&format!("__SpreadMacrosAnon{name}"), name.span())
+}
+
+fn expand(input: syn::DeriveInput) -> syn::Result<TokenStream> {
+    let syn::Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "`AnonFields` can only be derived on structs",
+        ));
+    };
+
+    if let syn::Fields::Unit = data.fields {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "`AnonFields` can't be derived on unit structs",
+        ));
+    }
+
+    // Tuple fields are named after their index (`_0`, `_1`, ..)
+    let members: Vec<syn::Member> = data
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(name) => syn::Member::Named(name.clone()),
+            None => syn::Member::Unnamed(syn::Index::from(i)),
+        })
+        .collect();
+    let names: Vec<_> = data
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(name) => name.clone(),
+            None => syn::Ident::new(&format!("_{i}"), Span::call_site()),
+        })
+        .collect();
+    let names_str: Vec<_> = names.iter().map(|name| name.to_string()).collect();
+    let types: Vec<_> = data.fields.iter().map(|field| &field.ty).collect();
+    let generics: Vec<_> = (0..names.len())
+        .map(|i| syn::Ident::new(&format!("T{i}"), Span::call_site()))
+        .collect();
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let anon_name = anon_struct_name(name);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // The struct has the same derives as the ones of `anon!`, and is debugged as `Anon`
+    Ok(quote! {
+        #[doc(hidden)]
+        #[derive(Copy, Clone, PartialEq, Eq)]
+        #vis struct #anon_name < #( #generics ),* > {
+            #( pub #names: #generics ),*
+        }
+
+        impl < #( #generics: ::core::fmt::Debug ),* > ::core::fmt::Debug
+        for #anon_name < #( #generics ),* > {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.debug_struct("Anon")
+                    #( .field(#names_str, &self.#names) )*
+                    .finish()
+            }
+        }
+
+        impl < #( #generics: ::core::clone::Clone ),* > #anon_name < #( &#generics ),* > {
+            #[doc(hidden)]
+            pub fn __spread_macros_cloned(self) -> #anon_name < #( #generics ),* > {
+                #anon_name {
+                    #( #names: ::core::clone::Clone::clone(self.#names) ),*
+                }
+            }
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            #[doc(hidden)]
+            pub fn __spread_macros_anon(self) -> #anon_name < #( #types ),* > {
+                #anon_name {
+                    #( #names: self.#members ),*
+                }
+            }
+
+            #[doc(hidden)]
+            pub fn __spread_macros_anon_ref(&self) -> #anon_name < #( &#types ),* > {
+                #anon_name {
+                    #( #names: &self.#members ),*
+                }
+            }
+
+            #[doc(hidden)]
+            pub fn __spread_macros_anon_mut(&mut self) -> #anon_name < #( &mut #types ),* > {
+                #anon_name {
+                    #( #names: &mut self.#members ),*
+                }
+            }
+        }
+    })
+}
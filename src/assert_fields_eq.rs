@@ -1,113 +1,364 @@
 use {
     crate::{common::*, *},
-    syn::bracketed,
+    syn::{bracketed, parenthesized, parse::discouraged::Speculative},
 };
 
 pub fn assert_fields_eq(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let assert_fields_eq = parse_macro_input!(tokens as AssertFieldsEq);
 
-    match assert_fields_eq {
-        AssertFieldsEq::List {
-            left,
+    if let Some(all) = &assert_fields_eq.all {
+        return syn::Error::new(all.span(), "`all` is only allowed in `assert_fields_ne!`")
+            .into_compile_error()
+            .into();
+    }
+
+    // Every mismatching field is listed in the message of the in-scope `assert_eq!`, or `assert!`
+    // when only mismatching fields are known
+    let message = assert_fields_eq.expand_message();
+
+    if let Some(mismatches) = assert_fields_eq.expand_except() {
+        return quote! {
+            {
+                #mismatches
+
+                assert!(mismatches.is_empty(), "{}:\n{}", #message, mismatches.join("\n"));
+            }
+        }
+        .into();
+    }
+
+    let (fields, bindings) = assert_fields_eq.expand_bindings();
+    let mismatches = expand_mismatches(&fields);
+
+    quote! {
+        {
+            #bindings
+
+            #mismatches
+
+            if !mismatches.is_empty() {
+                assert_eq!(left, right, "{}:\n{}", #message, mismatches.join("\n"));
+            }
+        }
+    }
+    .into()
+}
+
+// Statements binding `mismatches` to a list describing each field of `left` and `right` which
+// are not equal.
+pub fn expand_mismatches(fields: &[syn::Ident]) -> TokenStream {
+    let fields_str: Vec<_> = fields.iter().map(|field| field.to_string()).collect();
+
+    quote! {
+        let mut mismatches = ::std::vec::Vec::<::std::string::String>::new();
+        #(
+            if left.#fields != right.#fields {
+                mismatches.push(::std::format!(
+                    "  `{}`: left: {:?}, right: {:?}",
+                    #fields_str,
+                    left.#fields,
+                    right.#fields,
+                ));
+            }
+        )*
+    }
+}
+
+pub struct AssertFieldsEq {
+    pub left: syn::Expr,
+    // Some = `all [fields]` or `all { .. }`, every field must differ in `assert_fields_ne!`
+    pub all: Option<syn::Ident>,
+    pub expected: Expected,
+    pub fmt_args: TokenStream,
+}
+
+pub enum Expected {
+    List {
+        right: syn::Expr,
+        fields: Punctuated<ListEntry, Token![,]>,
+    },
+    // `right, except [fields]`, compares all fields of values deriving `CompareFields` except
+    // the listed ones
+    Except {
+        right: syn::Expr,
+        except: syn::Ident,
+        fields: Punctuated<syn::Member, Token![,]>,
+    },
+    Anon {
+        // None = only predicates
+        anon: Option<Box<crate::anon::Anon>>,
+        // Predicates are checked on the left value instead of being compared with the anon struct
+        predicates: Vec<Predicate>,
+        // Tolerance of `[approx(epsilon)] field: value` fields
+        approx: Vec<(syn::Ident, syn::Expr)>,
+    },
+}
+
+// `field => predicate` or `[regex("pattern")] field` in the anon form
+pub struct Predicate {
+    pub name: syn::Ident,
+    pub predicate: syn::Expr,
+    // `&'static str` shown instead of the expected value when the predicate doesn't hold
+    pub description: TokenStream,
+}
+
+impl AssertFieldsEq {
+    // Some = `except [fields]` form, statements binding `mismatches` to the list of mismatching
+    // fields which are not excepted, provided by the `CompareFields` derive.
+    pub fn expand_except(&self) -> Option<TokenStream> {
+        let Expected::Except {
             right,
+            except,
             fields,
-            fmt_args,
-        } => {
-            let fields: Vec<_> = fields.into_iter().collect();
-            quote! {
-                {
-                    #[allow(non_camel_case_types)]
-                    #[derive(Debug, PartialEq, Eq)]
-                    struct Fields
-                    <
-                        'a,
-                        #( #fields, )*
-                    > {
-                        #(#fields: &'a #fields,)*
-                    }
+        } = &self.expected
+        else {
+            return None;
+        };
+        let left = &self.left;
+        let names: Vec<_> = fields.iter().map(compare_fields::member_name).collect();
+        let fields = fields.iter();
+        // Missing `CompareFields` derive is reported on `except`
+        let method = quote_spanned! { except.span()=> __spread_macros_mismatches };
 
-                    let left = &#left;
-                    let left = Fields {
-                        #( #fields: & (left . #fields) ,)*
-                    };
+        Some(quote! {
+            let left = &#left;
+            let right = &#right;
+            // Excepted fields must exist
+            let _ = ( #( &left.#fields, )* );
+            let mismatches = left.#method(right, &[ #( #names ),* ]);
+        })
+    }
 
-                    let right = &#right;
-                    let right = Fields {
-                        #( #fields: & (right . #fields) ,)*
-                    };
+    // Message preceding the list of mismatches, custom messages are formatted without the
+    // leading comma.
+    pub fn expand_message(&self) -> TokenStream {
+        if self.fmt_args.is_empty() {
+            quote! { "fields are not equal" }
+        } else {
+            let fmt_args: TokenStream = self.fmt_args.clone().into_iter().skip(1).collect();
+            quote! { ::std::format!(#fmt_args) }
+        }
+    }
+
+    // Names of the compared fields, and statements binding `left` and `right` to structs of
+    // references to these fields, which can be compared and debugged.
+    pub fn expand_bindings(self) -> (Vec<syn::Ident>, TokenStream) {
+        let AssertFieldsEq { left, expected, .. } = self;
 
-                    assert_eq!(left, right #fmt_args);
+        let mut predicates = vec![];
+        let (entries, right_init, right): (Vec<_>, _, _) = match expected {
+            Expected::List { right, fields } => {
+                (fields.into_iter().collect(), None, quote! { &#right })
+            }
+            Expected::Except { .. } => unreachable!("handled by `expand_except`"),
+            Expected::Anon {
+                anon: None,
+                predicates: anon_predicates,
+                ..
+            } => {
+                predicates = anon_predicates;
+                (vec![], None, quote! { &() })
+            }
+            Expected::Anon {
+                anon: Some(anon),
+                predicates: anon_predicates,
+                approx,
+            } => {
+                predicates = anon_predicates;
+                let approx = |name: &syn::Ident| {
+                    approx
+                        .iter()
+                        .find(|(approx_name, _)| approx_name == name)
+                        .map(|(_, epsilon)| epsilon.clone())
+                };
+
+                let mut fields = vec![];
+
+                for field in &anon.items {
+                    match field {
+                        SpreadItem::Field(Field { name, .. }) => fields.push(ListEntry {
+                            approx: approx(name),
+                            name: name.clone(),
+                            call: None,
+                        }),
+                        SpreadItem::SpreadList(list) => {
+                            for field in list.fields_list.iter() {
+                                fields.push(ListEntry {
+                                    approx: None,
+                                    name: field.name.clone(),
+                                    call: None,
+                                })
+                            }
+                        }
+                        SpreadItem::FinalSpread(_, _, _) => {
+                            unreachable!("FinalSpread is not allowed in anon!")
+                        }
+                    }
                 }
+
+                let anon = anon.expand();
+
+                (
+                    fields,
+                    Some(quote! { let right = #anon; }),
+                    quote! { &right },
+                )
             }
-            .into()
+        };
+
+        // Fields are compared by reference, and getters by the value they return. Approximate
+        // fields are copied, and the tolerance is stored with the expected value.
+        let fields: Vec<_> = entries.iter().map(|entry| entry.name.clone()).collect();
+        let values = |source: TokenStream, is_right: bool| -> Vec<TokenStream> {
+            entries
+                .iter()
+                .map(|ListEntry { approx, name, call }| {
+                    let value = match call {
+                        Some(_) => quote! { #source . #name () },
+                        None if approx.is_some() => quote! { #source . #name },
+                        None => quote! { & (#source . #name) },
+                    };
+
+                    match approx {
+                        Some(epsilon) if is_right => {
+                            quote! { __Approx(#value, ::core::option::Option::Some(#epsilon)) }
+                        }
+                        Some(_) => quote! { __Approx(#value, ::core::option::Option::None) },
+                        None => value,
+                    }
+                })
+                .collect()
+        };
+        let mut left_values = values(quote! { left }, false);
+        let mut right_values = values(quote! { right }, true);
+
+        // A predicate field is equal to its expectation when the predicate holds, which makes it
+        // part of the same comparison and failure message as the other fields
+        let mut fields = fields;
+        for Predicate {
+            name,
+            predicate,
+            description,
+        } in &predicates
+        {
+            fields.push(name.clone());
+            left_values.push(quote! {
+                __Matches::Value(
+                    & (left . #name),
+                    __matches(& (left . #name), #predicate),
+                )
+            });
+            right_values.push(quote! {
+                __Matches::Predicate(#description)
+            });
         }
-        AssertFieldsEq::Anon {
-            left,
-            anon,
-            fmt_args,
-        } => {
-            let mut fields = vec![];
-
-            for field in &anon.items {
-                match field {
-                    SpreadItem::Field(Field { name, .. }) => fields.push(name.clone()),
-                    SpreadItem::SpreadList(list) => {
-                        for field in list.fields_list.iter() {
-                            fields.push(field.name.clone())
+
+        let matches = (!predicates.is_empty()).then(|| {
+            quote! {
+                enum __Matches<'a, T: ?Sized> {
+                    Value(&'a T, bool),
+                    Predicate(&'static str),
+                }
+
+                impl<T: ?Sized + ::core::fmt::Debug> ::core::fmt::Debug for __Matches<'_, T> {
+                    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                        match self {
+                            Self::Value(value, _) => ::core::fmt::Debug::fmt(value, f),
+                            Self::Predicate(predicate) => {
+                                ::core::write!(f, "<matches {}>", predicate)
+                            }
                         }
                     }
-                    SpreadItem::FinalSpread(_, _) => {
-                        unreachable!("FinalSpread is not allowed in anon!")
+                }
+
+                impl<T: ?Sized> ::core::cmp::PartialEq for __Matches<'_, T> {
+                    fn eq(&self, other: &Self) -> bool {
+                        match (self, other) {
+                            (Self::Value(_, holds), Self::Predicate(_))
+                            | (Self::Predicate(_), Self::Value(_, holds)) => *holds,
+                            _ => false,
+                        }
                     }
                 }
-            }
 
-            let anon = anon.expand();
+                impl<T: ?Sized> ::core::cmp::Eq for __Matches<'_, T> {}
 
+                fn __matches<T: ?Sized>(value: &T, predicate: impl FnOnce(&T) -> bool) -> bool {
+                    predicate(value)
+                }
+            }
+        });
+
+        let approx = entries.iter().any(|entry| entry.approx.is_some()).then(|| {
             quote! {
+                // Values are equal if their difference is within the tolerance of either side
+                struct __Approx<T>(T, ::core::option::Option<T>);
+
+                impl<T: ::core::fmt::Debug> ::core::fmt::Debug for __Approx<T> {
+                    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                        match &self.1 {
+                            ::core::option::Option::Some(epsilon) => {
+                                ::core::write!(f, "{:?} ± {:?}", self.0, epsilon)
+                            }
+                            ::core::option::Option::None => ::core::fmt::Debug::fmt(&self.0, f),
+                        }
+                    }
+                }
+
+                impl<T> ::core::cmp::PartialEq for __Approx<T>
+                where
+                    T: ::core::marker::Copy
+                        + ::core::cmp::PartialOrd
+                        + ::core::ops::Sub<Output = T>,
                 {
-                    let right = #anon;
-
-                    #[allow(non_camel_case_types)]
-                    #[derive(Debug, PartialEq, Eq)]
-                    struct Fields
-                    <
-                        'a,
-                        #( #fields, )*
-                    > {
-                        #(#fields: &'a #fields,)*
+                    fn eq(&self, other: &Self) -> bool {
+                        let difference = if self.0 > other.0 {
+                            self.0 - other.0
+                        } else {
+                            other.0 - self.0
+                        };
+
+                        match self.1.or(other.1) {
+                            ::core::option::Option::Some(epsilon) => difference <= epsilon,
+                            ::core::option::Option::None => self.0 == other.0,
+                        }
                     }
+                }
 
-                    let left = &#left;
-                    let left = Fields {
-                        #( #fields: & (left . #fields) ,)*
-                    };
+                impl<T> ::core::cmp::Eq for __Approx<T> where __Approx<T>: ::core::cmp::PartialEq {}
+            }
+        });
 
-                    let right = &right;
-                    let right = Fields {
-                        #( #fields: & (right . #fields) ,)*
-                    };
+        let bindings = quote! {
+            #right_init
 
-                    assert_eq!(left, right #fmt_args);
-                }
+            #approx
+
+            #matches
+
+            #[allow(non_camel_case_types)]
+            #[derive(Debug, PartialEq, Eq)]
+            struct Fields
+            <
+                #( #fields, )*
+            > {
+                #(#fields: #fields,)*
             }
-            .into()
-        }
-    }
-}
 
-enum AssertFieldsEq {
-    List {
-        left: syn::Expr,
-        right: syn::Expr,
-        fields: Punctuated<syn::Ident, Token![,]>,
-        fmt_args: TokenStream,
-    },
-    Anon {
-        left: syn::Expr,
-        anon: crate::anon::Anon,
-        fmt_args: TokenStream,
-    },
+            let left = &#left;
+            let left = Fields {
+                #( #fields: #left_values ,)*
+            };
+
+            #[allow(unused_variables)]
+            let right = #right;
+            let right = Fields {
+                #( #fields: #right_values ,)*
+            };
+        };
+
+        (fields, bindings)
+    }
 }
 
 impl Parse for AssertFieldsEq {
@@ -115,23 +366,162 @@ impl Parse for AssertFieldsEq {
         let left = input.parse()?;
         let _: Token![,] = input.parse()?;
 
+        let all = if input.peek(syn::Ident) && input.peek2(Brace) {
+            let all: syn::Ident = input.parse()?;
+            if all != "all" {
+                return Err(syn::Error::new(all.span(), "expected `all`"));
+            }
+            Some(all)
+        } else {
+            None
+        };
+
         let lookahead = input.lookahead1();
         if lookahead.peek(Brace) {
             let braced;
             braced!(braced in input);
 
-            let anon = braced.parse()?;
+            // `field => predicate` entries are taken out, and the other items are parsed as an
+            // anon struct
+            let mut anon_tokens = TokenStream::new();
+            let mut predicates = vec![];
+            while braced.peek(Token![#]) && braced.peek2(Token![!]) {
+                for _ in 0..3 {
+                    anon_tokens.extend([braced.parse::<proc_macro2::TokenTree>()?]);
+                }
+            }
+            if braced.peek(Token![pub]) || braced.peek(Token![struct]) {
+                anon_tokens.extend([braced.parse::<TokenStream>()?]);
+            }
+            let mut approx = vec![];
+            while !braced.is_empty() {
+                // Other brackets are a custom modifier `[path] field`
+                let matcher = if FieldMatcher::peek(&braced) {
+                    Some(braced.parse()?)
+                } else {
+                    None
+                };
+
+                if let Some(FieldMatcher::Regex(pattern)) = matcher {
+                    let name: syn::Ident = braced.parse()?;
+                    let description = format!("regex {:?}", pattern.value());
+                    let description = quote! { #description };
+                    let predicate = syn::parse_quote_spanned! { pattern.span()=>
+                        |value| ::regex::Regex::new(#pattern)
+                            .expect("invalid regex")
+                            .is_match(value)
+                    };
+                    predicates.push(Predicate {
+                        name,
+                        predicate,
+                        description,
+                    });
+                } else if braced.peek(syn::Ident) && braced.peek2(Token![=>]) {
+                    if let Some(FieldMatcher::Approx(epsilon)) = matcher {
+                        return Err(syn::Error::new(
+                            epsilon.span(),
+                            "`[approx(..)]` can't be applied to a predicate",
+                        ));
+                    }
+
+                    let name = braced.parse()?;
+                    let _: Token![=>] = braced.parse()?;
+                    let predicate: syn::Expr = braced.parse()?;
+                    let description = quote! { ::core::stringify!(#predicate) };
+                    predicates.push(Predicate {
+                        name,
+                        predicate,
+                        description,
+                    });
+                } else {
+                    if let Some(FieldMatcher::Approx(epsilon)) = matcher {
+                        let fork = braced.fork();
+                        let SpreadItem::Field(field) = fork.parse()? else {
+                            return Err(syn::Error::new(
+                                epsilon.span(),
+                                "`[approx(..)]` can only be applied to a field",
+                            ));
+                        };
+                        approx.push((field.name, epsilon));
+                    }
+
+                    let fork = braced.fork();
+                    let _: SpreadItem = fork.parse()?;
+                    while braced.cursor() != fork.cursor() {
+                        anon_tokens.extend([braced.parse::<proc_macro2::TokenTree>()?]);
+                    }
+                    anon_tokens.extend(quote! { , });
+                }
+
+                if braced.is_empty() {
+                    break;
+                }
+                let _: Token![,] = braced.parse()?;
+            }
+
+            let anon: Option<crate::anon::Anon> =
+                if anon_tokens.is_empty() && !predicates.is_empty() {
+                    None
+                } else {
+                    Some(syn::parse2(anon_tokens)?)
+                };
             let fmt_args = input.parse()?;
 
-            Ok(AssertFieldsEq::Anon {
+            if let Some((_, name)) = anon.as_ref().and_then(|anon| anon.item.as_ref()) {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "expectation must be an anonymous struct",
+                ));
+            }
+
+            Ok(AssertFieldsEq {
                 left,
-                anon,
+                all,
+                expected: Expected::Anon {
+                    anon: anon.map(Box::new),
+                    predicates,
+                    approx,
+                },
                 fmt_args,
             })
         } else if lookahead.peek(syn::Ident) {
             let right = input.parse()?;
             let _: Token![,] = input.parse()?;
 
+            if input.peek(syn::Ident) && input.peek2(syn::token::Bracket) {
+                let fork = input.fork();
+                let except: syn::Ident = fork.parse()?;
+                if except == "except" {
+                    input.advance_to(&fork);
+
+                    let bracketed;
+                    bracketed!(bracketed in input);
+                    let fields = Punctuated::parse_terminated(&bracketed)?;
+                    let fmt_args = input.parse()?;
+
+                    return Ok(AssertFieldsEq {
+                        left,
+                        all: None,
+                        expected: Expected::Except {
+                            right,
+                            except,
+                            fields,
+                        },
+                        fmt_args,
+                    });
+                }
+            }
+
+            let all = if input.peek(syn::Ident) && input.peek2(syn::token::Bracket) {
+                let all: syn::Ident = input.parse()?;
+                if all != "all" {
+                    return Err(syn::Error::new(all.span(), "expected `all`"));
+                }
+                Some(all)
+            } else {
+                None
+            };
+
             let bracketed;
             let bracket = bracketed!(bracketed in input);
 
@@ -146,10 +536,10 @@ impl Parse for AssertFieldsEq {
 
             let fmt_args = input.parse()?;
 
-            Ok(AssertFieldsEq::List {
+            Ok(AssertFieldsEq {
                 left,
-                right,
-                fields,
+                all,
+                expected: Expected::List { right, fields },
                 fmt_args,
             })
         } else {
@@ -157,3 +547,101 @@ impl Parse for AssertFieldsEq {
         }
     }
 }
+
+// `field` or `getter()` in a list of fields, optionally prefixed by `[approx(epsilon)]`
+pub struct ListEntry {
+    pub approx: Option<syn::Expr>,
+    pub name: syn::Ident,
+    pub call: Option<syn::token::Paren>,
+}
+
+impl Parse for ListEntry {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let approx = if input.peek(syn::token::Bracket) {
+            match input.parse()? {
+                FieldMatcher::Approx(epsilon) => Some(epsilon),
+                FieldMatcher::Regex(pattern) => {
+                    return Err(syn::Error::new(
+                        pattern.span(),
+                        "`[regex(..)]` is only allowed in the anonymous struct form",
+                    ))
+                }
+            }
+        } else {
+            None
+        };
+        let name = input.parse()?;
+
+        let call = if input.peek(syn::token::Paren) {
+            let args;
+            let paren = parenthesized!(args in input);
+            if !args.is_empty() {
+                return Err(syn::Error::new(
+                    args.span(),
+                    "only getters without arguments are supported",
+                ));
+            }
+            Some(paren)
+        } else {
+            None
+        };
+
+        Ok(Self { approx, name, call })
+    }
+}
+
+// `[approx(epsilon)]` or `[regex("pattern")]` before a field
+pub enum FieldMatcher {
+    Approx(syn::Expr),
+    Regex(syn::LitStr),
+}
+
+impl FieldMatcher {
+    fn peek(input: ParseStream) -> bool {
+        if !input.peek(syn::token::Bracket) {
+            return false;
+        }
+
+        let Ok(group) = input.fork().parse::<proc_macro2::Group>() else {
+            return false;
+        };
+        let mut tokens = group.stream().into_iter();
+
+        matches!(
+            (tokens.next(), tokens.next()),
+            (
+                Some(proc_macro2::TokenTree::Ident(ident)),
+                Some(proc_macro2::TokenTree::Group(args)),
+            ) if (ident == "approx" || ident == "regex")
+                && args.delimiter() == proc_macro2::Delimiter::Parenthesis
+        )
+    }
+}
+
+impl Parse for FieldMatcher {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let bracketed;
+        bracketed!(bracketed in input);
+
+        let matcher: syn::Ident = bracketed.parse()?;
+        let args;
+        parenthesized!(args in bracketed);
+
+        match matcher.to_string().as_str() {
+            "approx" => Ok(Self::Approx(args.parse()?)),
+            "regex" => {
+                if !cfg!(feature = "regex") {
+                    return Err(syn::Error::new(
+                        matcher.span(),
+                        "`regex` requires the `regex` feature of `spread_macros`",
+                    ));
+                }
+                Ok(Self::Regex(args.parse()?))
+            }
+            _ => Err(syn::Error::new(
+                matcher.span(),
+                "expected `approx(epsilon)` or `regex(\"pattern\")`",
+            )),
+        }
+    }
+}
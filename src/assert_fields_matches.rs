@@ -0,0 +1,113 @@
+use crate::*;
+
+pub fn assert_fields_matches(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let AssertFieldsMatches {
+        left,
+        fields,
+        fmt_args,
+    } = parse_macro_input!(tokens as AssertFieldsMatches);
+
+    // Custom messages are formatted without the leading comma, and followed by the mismatches
+    let message = if fmt_args.is_empty() {
+        quote! { "fields don't match the patterns" }
+    } else {
+        let fmt_args: TokenStream = fmt_args.into_iter().skip(1).collect();
+        quote! { ::std::format!(#fmt_args) }
+    };
+
+    let checks = fields.iter().map(
+        |FieldPattern {
+             name,
+             pattern,
+             guard,
+         }| {
+            let name_str = name.to_string();
+            let guard = guard
+                .as_ref()
+                .map(|(if_token, guard)| quote! { #if_token #guard });
+            quote! {
+                if !::core::matches!(& (left . #name), #pattern #guard) {
+                    mismatches.push(::std::format!(
+                        "  `{}`: {:?} doesn't match `{}`",
+                        #name_str,
+                        left . #name,
+                        ::core::stringify!(#pattern),
+                    ));
+                }
+            }
+        },
+    );
+
+    quote! {
+        {
+            let left = &#left;
+
+            let mut mismatches = ::std::vec::Vec::<::std::string::String>::new();
+            #( #checks )*
+
+            if !mismatches.is_empty() {
+                ::core::panic!("{}:\n{}", #message, mismatches.join("\n"));
+            }
+        }
+    }
+    .into()
+}
+
+struct AssertFieldsMatches {
+    left: syn::Expr,
+    fields: Punctuated<FieldPattern, Token![,]>,
+    fmt_args: TokenStream,
+}
+
+impl Parse for AssertFieldsMatches {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let left = input.parse()?;
+        let _: Token![,] = input.parse()?;
+
+        let braced;
+        let brace = braced!(braced in input);
+        let fields = Punctuated::parse_terminated(&braced)?;
+
+        if fields.is_empty() {
+            return Err(syn::Error::new(
+                brace.span.join(),
+                "patterns list cannot be empty",
+            ));
+        }
+
+        let fmt_args = input.parse()?;
+
+        Ok(Self {
+            left,
+            fields,
+            fmt_args,
+        })
+    }
+}
+
+// `field: Pattern`, the pattern is matched against a reference to the field
+struct FieldPattern {
+    name: syn::Ident,
+    pattern: syn::Pat,
+    // Some = `field: Pattern if guard`
+    guard: Option<(Token![if], syn::Expr)>,
+}
+
+impl Parse for FieldPattern {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        let _: Token![:] = input.parse()?;
+        let pattern = syn::Pat::parse_multi_with_leading_vert(input)?;
+        let guard = if input.peek(Token![if]) {
+            Some((input.parse()?, input.parse()?))
+        } else {
+            None
+        };
+
+        Ok(Self {
+            name,
+            pattern,
+            guard,
+        })
+    }
+}
@@ -0,0 +1,53 @@
+use crate::{assert_fields_eq::AssertFieldsEq, *};
+
+pub fn assert_fields_ne(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let assert_fields_ne = parse_macro_input!(tokens as AssertFieldsEq);
+
+    let all = assert_fields_ne.all.is_some();
+    let fmt_args = assert_fields_ne.fmt_args.clone();
+
+    if let Some(mismatches) = assert_fields_ne.expand_except() {
+        let fmt_args = if fmt_args.is_empty() {
+            quote! { , "all compared fields are equal" }
+        } else {
+            fmt_args
+        };
+
+        return quote! {
+            {
+                #mismatches
+
+                assert!(!mismatches.is_empty() #fmt_args);
+            }
+        }
+        .into();
+    }
+    let (fields, bindings) = assert_fields_ne.expand_bindings();
+
+    let assertions = if all {
+        // Each field is checked separately, reporting which one is equal
+        let assertions = fields.iter().map(|field| {
+            let fmt_args = if fmt_args.is_empty() {
+                let message = format!("`{field}` is equal in both values");
+                quote! { , #message }
+            } else {
+                fmt_args.clone()
+            };
+
+            quote! { assert_ne!(left.#field, right.#field #fmt_args); }
+        });
+
+        quote! { #( #assertions )* }
+    } else {
+        quote! { assert_ne!(left, right #fmt_args); }
+    };
+
+    quote! {
+        {
+            #bindings
+
+            #assertions
+        }
+    }
+    .into()
+}
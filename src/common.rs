@@ -1,15 +1,32 @@
-use {super::*, syn::ext::IdentExt};
+use {
+    super::*,
+    syn::{ext::IdentExt, parse::discouraged::Speculative},
+};
 
 pub enum SpreadItem {
     Field(Field),
     SpreadList(SpreadList),
-    FinalSpread(Token![..], syn::Expr),
+    // Some = `..>remaining`, converted with `Into`
+    FinalSpread(Token![..], Option<Token![>]>, syn::Expr),
 }
 
 pub struct Field {
     pub is_mut: Option<Token![mut]>,
     pub modifier: Option<SpreadModifier>,
     pub name: syn::Ident,
+    // Some = name of the field in the source when renamed by `{ field as name } in source` or
+    // `{ .. } in source as prefix_*`
+    pub member: Option<syn::Ident>,
+    // Some = tuple index `0 as name` in a spread list
+    pub index: Option<syn::Index>,
+    // Some = element `[N] name` of an array or slice in a spread list
+    pub element: Option<syn::LitInt>,
+    // Some = getter method call `name()` in a spread list
+    pub call: Option<syn::token::Paren>,
+    // Some = `name @ Type` or `name: Type = value`, value is ascribed this type
+    pub ty: Option<Box<syn::Type>>,
+    // Some = `name ?: value`, value is an `Option` falling back to `..remaining`
+    pub optional: Option<Token![?]>,
     pub value: Option<syn::Expr>,
 }
 
@@ -20,12 +37,27 @@ pub enum SpreadModifier {
     Into(Token![>]),
     Clone(Token![+]),
     CloneInto(Token![+], Token![>]),
+    CowBorrowed(Token![&], Token![~]),
+    CowOwned(Token![~]),
+    CloneCowOwned(Token![+], Token![~]),
+    UnwrapOrDefault(Token![?]),
+    CloneUnwrapOrDefault(Token![+], Token![?]),
+    // `^`, upgrades a `Weak` and panics if the value was dropped
+    Upgrade(Token![^]),
+    // `^?`, upgrades a `Weak` and returns `Default::default()` if the value was dropped
+    UpgradeOrReturn(Token![^], Token![?]),
+    // `+&`, clones an `Arc` or `Rc` handle without cloning the data
+    CloneShared(Token![+], Token![&]),
+    // `assert` or `debug_assert` with a predicate
+    Assert(syn::Ident, Box<syn::Expr>),
     Custom(syn::Path),
     CustomRef(syn::Path, Token![&]),
     CustomRefMut(syn::Path, Token![&], Token![mut]),
 }
 
 pub struct SpreadList {
+    // Some = `map { .. } in source`, fields are looked up by name in a map
+    pub map: Option<syn::Ident>,
     pub fields_list: Punctuated<Field, Token![,]>,
     pub source: syn::Expr,
     pub source_ident: syn::Ident,
@@ -35,12 +67,46 @@ impl Parse for SpreadItem {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let lookahead = input.lookahead1();
 
-        if lookahead.peek(Brace) {
+        if lookahead.peek(Brace) || SpreadList::peek_map(input) {
             input.parse().map(SpreadItem::SpreadList)
         } else if lookahead.peek(Token![..]) {
-            Ok(SpreadItem::FinalSpread(input.parse()?, input.parse()?))
+            Ok(SpreadItem::FinalSpread(
+                input.parse()?,
+                input.parse()?,
+                input.parse()?,
+            ))
         } else {
-            Ok(SpreadItem::Field(input.parse()?))
+            let field: Field = input.parse()?;
+
+            if let Some(paren) = field.call {
+                return Err(syn::Error::new(
+                    paren.span.join(),
+                    "method calls are only allowed in spread lists `{ method() } in source`",
+                ));
+            }
+
+            if let Some(index) = &field.index {
+                return Err(syn::Error::new(
+                    index.span(),
+                    "tuple indices are only allowed in spread lists `{ 0 as name } in source`",
+                ));
+            }
+
+            if let Some(element) = &field.element {
+                return Err(syn::Error::new(
+                    element.span(),
+                    "elements are only allowed in spread lists `{ [0] name } in source`",
+                ));
+            }
+
+            if let Some(member) = &field.member {
+                return Err(syn::Error::new(
+                    member.span(),
+                    "renaming is only allowed in spread lists `{ field as name } in source`",
+                ));
+            }
+
+            Ok(SpreadItem::Field(field))
         }
     }
 }
@@ -56,25 +122,40 @@ impl SpreadItem {
                 }
             },
             Self::SpreadList(spread_list) => spread_list.field_expansion(),
-            Self::FinalSpread(token_dotdot, source) => {
+            Self::FinalSpread(token_dotdot, None, source) => {
                 quote! { #token_dotdot #source }
             }
+            Self::FinalSpread(token_dotdot, Some(token_into), source) => {
+                let into = quote_spanned!(token_into.span()=> ::core::convert::Into::into);
+                quote! { #token_dotdot #into ( #source ) }
+            }
         }
     }
 
-    pub fn let_expansion(&self) -> TokenStream {
+    // Names and values with modifiers applied of all fields of this item, for expansions that
+    // are not struct literals.
+    pub fn values_expansion(&self) -> Vec<(&syn::Ident, TokenStream)> {
         match self {
             Self::Field(field) => {
-                let source = field.name.clone();
-                let is_mut = field.is_mut;
-                let expansion = match &field.value {
+                let value = match &field.value {
                     Some(value) => field.value_with_modifiers(quote! { #value }),
-                    None => field.value_with_modifiers(quote! { #source }),
+                    None => {
+                        let source = field.name.clone();
+                        field.value_with_modifiers(quote! { #source })
+                    }
                 };
-                quote!( let #is_mut #source = #expansion; )
+                vec![(&field.name, value)]
             }
+            Self::SpreadList(spread_list) => spread_list.values_expansion(),
+            Self::FinalSpread(_, _, _) => vec![],
+        }
+    }
+
+    pub fn let_expansion(&self) -> TokenStream {
+        match self {
+            Self::Field(field) => field.let_expansion(None),
             Self::SpreadList(spread_list) => spread_list.let_expansion(),
-            Self::FinalSpread(dotdot, _) => {
+            Self::FinalSpread(dotdot, _, _) => {
                 syn::Error::new(dotdot.span(), "`..remaining` is not allowed in this macro")
                     .to_compile_error()
             }
@@ -94,7 +175,10 @@ impl SpreadModifier {
             if lookahead.peek(Token![mut]) {
                 let token_mut = input.parse()?;
                 Some(SpreadModifier::RefMut(token_ref, token_mut))
-            } else if lookahead.peek(syn::Ident::peek_any) {
+            } else if lookahead.peek(Token![~]) {
+                let token_cow = input.parse()?;
+                Some(SpreadModifier::CowBorrowed(token_ref, token_cow))
+            } else if lookahead.peek(syn::Ident::peek_any) || lookahead.peek(syn::LitInt) {
                 // don't parse it now
                 Some(SpreadModifier::Ref(token_ref))
             } else {
@@ -103,6 +187,22 @@ impl SpreadModifier {
         } else if lookahead.peek(Token![>]) {
             let token_into = input.parse()?;
             Some(SpreadModifier::Into(token_into))
+        } else if lookahead.peek(Token![~]) {
+            let token_cow = input.parse()?;
+            Some(SpreadModifier::CowOwned(token_cow))
+        } else if lookahead.peek(Token![?]) {
+            let token_unwrap = input.parse()?;
+            Some(SpreadModifier::UnwrapOrDefault(token_unwrap))
+        } else if lookahead.peek(Token![^]) {
+            let token_upgrade = input.parse()?;
+            if input.peek(Token![?]) {
+                Some(SpreadModifier::UpgradeOrReturn(
+                    token_upgrade,
+                    input.parse()?,
+                ))
+            } else {
+                Some(SpreadModifier::Upgrade(token_upgrade))
+            }
         } else if lookahead.peek(Token![+]) {
             let token_clone = input.parse()?;
 
@@ -111,7 +211,19 @@ impl SpreadModifier {
             if lookahead.peek(Token![>]) {
                 let token_into = input.parse()?;
                 Some(SpreadModifier::CloneInto(token_clone, token_into))
-            } else if lookahead.peek(syn::Ident::peek_any) {
+            } else if lookahead.peek(Token![~]) {
+                let token_cow = input.parse()?;
+                Some(SpreadModifier::CloneCowOwned(token_clone, token_cow))
+            } else if lookahead.peek(Token![?]) {
+                let token_unwrap = input.parse()?;
+                Some(SpreadModifier::CloneUnwrapOrDefault(
+                    token_clone,
+                    token_unwrap,
+                ))
+            } else if lookahead.peek(Token![&]) {
+                let token_ref = input.parse()?;
+                Some(SpreadModifier::CloneShared(token_clone, token_ref))
+            } else if lookahead.peek(syn::Ident::peek_any) || lookahead.peek(syn::LitInt) {
                 // don't parse it now
                 Some(SpreadModifier::Clone(token_clone))
             } else {
@@ -120,6 +232,20 @@ impl SpreadModifier {
         } else if lookahead.peek(syn::token::Bracket) {
             let bracket_content;
             let _brackets = syn::bracketed!(bracket_content in input);
+
+            if SpreadModifier::peek_assert(&bracket_content) {
+                let assert = bracket_content.parse()?;
+                let paren_content;
+                syn::parenthesized!(paren_content in bracket_content);
+                let predicate = paren_content.parse()?;
+
+                if !bracket_content.is_empty() {
+                    return Err(bracket_content.error("unexpected tokens after assertion"));
+                }
+
+                return Ok(Some(SpreadModifier::Assert(assert, Box::new(predicate))));
+            }
+
             let custom_path = bracket_content.parse()?;
 
             let lookahead = input.lookahead1();
@@ -136,7 +262,7 @@ impl SpreadModifier {
                         token_ref,
                         token_mut,
                     ))
-                } else if lookahead.peek(syn::Ident::peek_any) {
+                } else if lookahead.peek(syn::Ident::peek_any) || lookahead.peek(syn::LitInt) {
                     // don't parse it now
                     Some(SpreadModifier::CustomRef(custom_path, token_ref))
                 } else {
@@ -145,7 +271,7 @@ impl SpreadModifier {
             } else {
                 Some(SpreadModifier::Custom(custom_path))
             }
-        } else if lookahead.peek(syn::Ident::peek_any) {
+        } else if lookahead.peek(syn::Ident::peek_any) || lookahead.peek(syn::LitInt) {
             // don't parse it now
             None
         } else {
@@ -154,6 +280,15 @@ impl SpreadModifier {
 
         Ok(modifier)
     }
+
+    fn peek_assert(input: ParseStream) -> bool {
+        input.peek(syn::Ident)
+            && input.peek2(syn::token::Paren)
+            && input
+                .fork()
+                .parse::<syn::Ident>()
+                .is_ok_and(|ident| ident == "assert" || ident == "debug_assert")
+    }
 }
 
 impl Parse for Field {
@@ -167,17 +302,104 @@ impl Parse for Field {
             }
         };
 
+        // `[N]` is an element, while other brackets are a custom modifier `[path]`
+        let element = if input.peek(syn::token::Bracket) && Field::peek_element(input) {
+            let bracketed;
+            syn::bracketed!(bracketed in input);
+            Some(bracketed.parse()?)
+        } else {
+            None
+        };
+
         let modifier = SpreadModifier::parse(input)?;
 
-        let name = input.parse()?;
+        let (index, name) = if element.is_none() && input.peek(syn::LitInt) {
+            let index = input.parse()?;
+            let _: Token![as] = input.parse()?;
+            (Some(index), input.parse()?)
+        } else {
+            (None, input.parse()?)
+        };
+
+        let call = if index.is_none() && element.is_none() && input.peek(syn::token::Paren) {
+            let paren_content;
+            let paren = syn::parenthesized!(paren_content in input);
+
+            if !paren_content.is_empty() {
+                return Err(syn::Error::new(
+                    paren.span.join(),
+                    "only methods without arguments can be called",
+                ));
+            }
+
+            Some(paren)
+        } else {
+            None
+        };
+
+        // `name as renamed`, the field is read from `name`
+        let (member, name) = if index.is_none() && element.is_none() && input.peek(Token![as]) {
+            let _: Token![as] = input.parse()?;
+            (Some(name), input.parse()?)
+        } else {
+            (None, name)
+        };
+
+        let mut ty: Option<Box<syn::Type>> = if input.peek(Token![@]) {
+            let _: Token![@] = input.parse()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        let optional = if input.peek(Token![?]) && input.peek2(Token![:]) {
+            Some(input.parse()?)
+        } else {
+            None
+        };
 
         let value = {
             let lookahead = input.lookahead1();
 
             if lookahead.peek(Token![:]) {
                 let _: Token![:] = input.parse()?;
-                let value = input.parse()?;
-                Some(value)
+
+                // `name: Type = value`
+                let fork = input.fork();
+                if optional.is_none()
+                    && fork.parse::<syn::Type>().is_ok()
+                    && fork.peek(Token![=])
+                    && !fork.peek(Token![==])
+                {
+                    if let Some(ty) = &ty {
+                        return Err(syn::Error::new(
+                            ty.span(),
+                            "type can't be provided both with `@ Type` and `name: Type = value`",
+                        ));
+                    }
+
+                    ty = Some(input.parse()?);
+                    let _: Token![=] = input.parse()?;
+                }
+
+                // `name: { .. }` which isn't a valid block is a nested `anon!`
+                if input.peek(Brace) && input.fork().parse::<syn::Expr>().is_err() {
+                    let braced;
+                    braced!(braced in input);
+                    let anon: anon::Anon = braced.parse()?;
+
+                    if anon.item.is_some() {
+                        return Err(syn::Error::new(
+                            braced.span(),
+                            "nested anonymous structs cannot declare a struct",
+                        ));
+                    }
+
+                    Some(syn::Expr::Verbatim(anon.expand()))
+                } else {
+                    let value = input.parse()?;
+                    Some(value)
+                }
             } else {
                 None
             }
@@ -187,12 +409,83 @@ impl Parse for Field {
             is_mut,
             modifier,
             name,
+            member,
+            index,
+            element,
+            call,
+            ty,
+            optional,
             value,
         })
     }
 }
 
 impl Field {
+    // `let` binding of the field, with `?` applied to the value for `try name: value`
+    pub fn let_expansion(&self, token_try: Option<&Token![try]>) -> TokenStream {
+        let source = &self.name;
+        let is_mut = self.is_mut;
+        let expansion = match (&self.value, token_try) {
+            (Some(value), Some(token_try)) => {
+                let value = quote_spanned!(token_try.span()=> (#value)?);
+                self.value_with_modifiers_untyped(value)
+            }
+            (Some(value), None) => self.value_with_modifiers_untyped(quote! { #value }),
+            (None, _) => self.value_with_modifiers_untyped(quote! { #source }),
+        };
+        let ty = self.ty.as_ref().map(|ty| quote! { : #ty });
+        quote!( let #is_mut #source #ty = #expansion; )
+    }
+
+    // `name ?: value` expansion, taking the field from `base` if value is `None`. The value is
+    // evaluated into `temp` by the returned binding, before `base` is bound.
+    pub fn optional_field_expansion(
+        &self,
+        temp: &syn::Ident,
+        base: &syn::Ident,
+    ) -> (TokenStream, TokenStream) {
+        let name = &self.name;
+        let value = &self.value;
+        let value_with_modifiers = self.value_with_modifiers(quote! { __value });
+
+        let binding = quote! {
+            let #temp = match #value {
+                ::core::option::Option::Some(__value) => {
+                    ::core::option::Option::Some(#value_with_modifiers)
+                }
+                ::core::option::Option::None => ::core::option::Option::None,
+            };
+        };
+        let expansion = quote! {
+            #name: match #temp {
+                ::core::option::Option::Some(__value) => __value,
+                ::core::option::Option::None => #base . #name,
+            }
+        };
+
+        (binding, expansion)
+    }
+
+    // The whole access is spanned on the field name so that errors about a missing field or a
+    // wrong type point to the field in the macro input rather than to the source.
+    pub fn access(&self, source: &syn::Ident) -> TokenStream {
+        let name = self.member.as_ref().unwrap_or(&self.name);
+        let source = syn::Ident::new(&source.to_string(), name.span());
+        match (&self.index, &self.element, self.call) {
+            (Some(index), _, _) => quote! { #source . #index },
+            (None, Some(element), _) => quote_spanned! { name.span()=> #source [#element] },
+            (None, None, Some(_)) => quote! { #source . #name () },
+            (None, None, None) => quote! { #source . #name },
+        }
+    }
+
+    fn peek_element(input: ParseStream) -> bool {
+        input
+            .fork()
+            .parse::<proc_macro2::Group>()
+            .is_ok_and(|group| syn::parse2::<syn::LitInt>(group.stream()).is_ok())
+    }
+
     fn field_expansion(&self, source: proc_macro2::TokenStream) -> TokenStream {
         let name = &self.name;
         let value_with_modifiers = self.value_with_modifiers(source);
@@ -200,7 +493,40 @@ impl Field {
         quote! { #name: #value_with_modifiers }
     }
 
+    // Whether the modifier only needs a reference to the value
+    fn is_borrowing(&self) -> bool {
+        matches!(
+            self.modifier,
+            Some(
+                SpreadModifier::Ref(_)
+                    | SpreadModifier::Clone(_)
+                    | SpreadModifier::CloneInto(_, _)
+                    | SpreadModifier::CowBorrowed(_, _)
+                    | SpreadModifier::CloneCowOwned(_, _)
+                    | SpreadModifier::CloneUnwrapOrDefault(_, _)
+                    | SpreadModifier::CloneShared(_, _)
+                    | SpreadModifier::Upgrade(_)
+                    | SpreadModifier::UpgradeOrReturn(_, _)
+                    | SpreadModifier::CustomRef(_, _)
+            )
+        )
+    }
+
     pub fn value_with_modifiers(&self, source: proc_macro2::TokenStream) -> TokenStream {
+        let value = self.value_with_modifiers_untyped(source);
+
+        // Ascribed type drives the inference of modifiers such as `>`
+        match &self.ty {
+            Some(ty) => {
+                let identity = quote_spanned!(ty.span()=> ::core::convert::identity);
+                quote! { #identity::<#ty>(#value) }
+            }
+            None => value,
+        }
+    }
+
+    // Value with modifiers applied without the ascribed type, for bindings that have a type
+    fn value_with_modifiers_untyped(&self, source: proc_macro2::TokenStream) -> TokenStream {
         match &self.modifier {
             Some(SpreadModifier::Ref(token_ref)) => {
                 quote! { #token_ref #source }
@@ -221,6 +547,92 @@ impl Field {
                 let into = quote_spanned!(token_into.span()=> .into());
                 quote! { #source #clone #into }
             }
+            Some(SpreadModifier::CowBorrowed(token_ref, token_cow)) => {
+                let cow = quote_spanned!(token_cow.span()=> ::std::borrow::Cow::Borrowed);
+                quote! { #cow ( #token_ref #source ) }
+            }
+            Some(SpreadModifier::CowOwned(token_cow)) => {
+                let cow = quote_spanned!(token_cow.span()=> ::std::borrow::Cow::Owned);
+                quote! { #cow ( #source ) }
+            }
+            Some(SpreadModifier::CloneCowOwned(token_clone, token_cow)) => {
+                let clone = quote_spanned!(token_clone.span()=> .clone());
+                let cow = quote_spanned!(token_cow.span()=> ::std::borrow::Cow::Owned);
+                quote! { #cow ( #source #clone ) }
+            }
+            Some(SpreadModifier::UnwrapOrDefault(token_unwrap)) => {
+                let unwrap = quote_spanned!(token_unwrap.span()=> .unwrap_or_default());
+                quote! { #source #unwrap }
+            }
+            Some(SpreadModifier::CloneUnwrapOrDefault(token_clone, token_unwrap)) => {
+                let clone = quote_spanned!(token_clone.span()=> .clone());
+                let unwrap = quote_spanned!(token_unwrap.span()=> .unwrap_or_default());
+                quote! { #source #clone #unwrap }
+            }
+            Some(SpreadModifier::Upgrade(token_upgrade)) => {
+                let message = format!("`{}` was dropped", self.name.unraw());
+                let upgrade = quote_spanned!(token_upgrade.span()=> .upgrade());
+                quote! { #source #upgrade .expect(#message) }
+            }
+            Some(SpreadModifier::UpgradeOrReturn(token_upgrade, token_return)) => {
+                let upgrade = quote_spanned!(token_upgrade.span()=> .upgrade());
+                let default =
+                    quote_spanned!(token_return.span()=> ::core::default::Default::default());
+                quote! {
+                    match #source #upgrade {
+                        ::core::option::Option::Some(__value) => __value,
+                        ::core::option::Option::None => return #default,
+                    }
+                }
+            }
+            Some(SpreadModifier::CloneShared(token_clone, token_ref)) => {
+                // Local trait only implemented by shared pointers, so data is never deep cloned
+                let span = token_clone
+                    .span()
+                    .join(token_ref.span())
+                    .unwrap_or(token_clone.span());
+                quote_spanned! {span=>
+                    {
+                        trait SharedPointer {
+                            fn clone_pointer(&self) -> Self;
+                        }
+
+                        impl<T: ?Sized> SharedPointer for ::std::sync::Arc<T> {
+                            fn clone_pointer(&self) -> Self {
+                                ::std::sync::Arc::clone(self)
+                            }
+                        }
+
+                        impl<T: ?Sized> SharedPointer for ::std::rc::Rc<T> {
+                            fn clone_pointer(&self) -> Self {
+                                ::std::rc::Rc::clone(self)
+                            }
+                        }
+
+                        SharedPointer::clone_pointer(&#source)
+                    }
+                }
+            }
+            Some(SpreadModifier::Assert(assert, predicate)) => {
+                let message = format!(
+                    "invalid value for field `{}`: {}",
+                    self.name.unraw(),
+                    quote! { #predicate }
+                );
+                // Calling the predicate through a function allows the closure argument type to be
+                // inferred.
+                quote! {
+                    {
+                        fn __check<T>(value: &T, predicate: impl FnOnce(&T) -> bool) -> bool {
+                            predicate(value)
+                        }
+
+                        let __value = #source;
+                        #assert!(__check(&__value, #predicate), "{}", #message);
+                        __value
+                    }
+                }
+            }
             Some(SpreadModifier::Custom(path)) => {
                 quote! { #path ( #source )}
             }
@@ -237,22 +649,87 @@ impl Field {
 
 impl Parse for SpreadList {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        let map = if SpreadList::peek_map(input) {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
         let braced;
         braced!(braced in input);
 
-        let fields_list = Punctuated::<Field, _>::parse_terminated(&braced)?;
+        let mut fields_list = Punctuated::<Field, _>::parse_terminated(&braced)?;
+
+        if map.is_some() {
+            for field in fields_list.iter() {
+                if let Some(paren) = field.call {
+                    return Err(syn::Error::new(
+                        paren.span.join(),
+                        "method calls are not allowed in map spread lists",
+                    ));
+                }
+
+                if let Some(index) = &field.index {
+                    return Err(syn::Error::new(
+                        index.span(),
+                        "tuple indices are not allowed in map spread lists",
+                    ));
+                }
+
+                if let Some(element) = &field.element {
+                    return Err(syn::Error::new(
+                        element.span(),
+                        "elements are not allowed in map spread lists",
+                    ));
+                }
+            }
+        }
+
         let _: Token![in] = input.parse()?;
-        let source: syn::Expr = input.parse()?;
+
+        let source = SpreadList::parse_source(input)?;
+
+        // `source else fallback` unwraps an `Option` or `Result` source
+        let source = if input.peek(Token![else]) {
+            let token_else: Token![else] = input.parse()?;
+            let fallback = SpreadList::parse_source(input)?;
+            let unwrap_or = quote_spanned!(token_else.span()=> .unwrap_or);
+            syn::Expr::Verbatim(quote! { (#source) #unwrap_or (#fallback) })
+        } else {
+            source
+        };
+
+        if SpreadList::peek_rename(input) {
+            let _: Token![as] = input.parse()?;
+            let (prefix, suffix) = if input.peek(Token![*]) {
+                let _: Token![*] = input.parse()?;
+                (String::new(), input.parse::<syn::Ident>()?.to_string())
+            } else {
+                let prefix = input.parse::<syn::Ident>()?.to_string();
+                let _: Token![*] = input.parse()?;
+                (prefix, String::new())
+            };
+
+            for field in fields_list.iter_mut() {
+                let renamed = format!("{prefix}{}{suffix}", field.name.unraw());
+                let renamed = syn::Ident::new(&renamed, field.name.span());
+                let name = std::mem::replace(&mut field.name, renamed);
+                field.member.get_or_insert(name);
+            }
+        }
 
         let source_ident: String = fields_list
             .iter()
             .fold(String::from("_"), |mut buf, field| {
-                write!(buf, "_{}", field.name).expect("to write String");
+                // Leading underscores are trimmed to keep a snake case name (`_0` fields)
+                let name = field.name.unraw().to_string();
+                write!(buf, "_{}", name.trim_start_matches('_')).expect("to write String");
                 buf
             });
         let source_ident = syn::Ident::new(&source_ident, source.span());
 
         Ok(SpreadList {
+            map,
             fields_list,
             source,
             source_ident,
@@ -261,30 +738,146 @@ impl Parse for SpreadList {
 }
 
 impl SpreadList {
+    // `source as prefix_*` is not a valid expression, so it is only looked for if the source
+    // can't be parsed until the next item or `else`.
+    fn parse_source(input: ParseStream) -> syn::Result<syn::Expr> {
+        let fork = input.fork();
+        match fork.parse() {
+            Ok(source) if fork.is_empty() || fork.peek(Token![,]) || fork.peek(Token![else]) => {
+                input.advance_to(&fork);
+                Ok(source)
+            }
+            _ => {
+                let mut source = TokenStream::new();
+                while !input.is_empty()
+                    && !input.peek(Token![else])
+                    && !SpreadList::peek_rename(input)
+                {
+                    source.extend([input.parse::<proc_macro2::TokenTree>()?]);
+                }
+                syn::parse2(source)
+            }
+        }
+    }
+
+    fn peek_rename(input: ParseStream) -> bool {
+        input.peek(Token![as])
+            && ((input.peek2(syn::Ident) && input.peek3(Token![*]))
+                || (input.peek2(Token![*]) && input.peek3(syn::Ident)))
+    }
+
+    fn peek_map(input: ParseStream) -> bool {
+        input.peek(syn::Ident)
+            && input.peek2(Brace)
+            && input
+                .fork()
+                .parse::<syn::Ident>()
+                .is_ok_and(|ident| ident == "map")
+    }
+
+    // Entries are removed from a map source which isn't a `&map` reference
+    fn is_owned_map(&self) -> bool {
+        self.map.is_some() && !matches!(self.source, syn::Expr::Reference(_))
+    }
+
+    fn removes_entries(&self) -> bool {
+        self.is_owned_map() && !self.fields_list.iter().all(Field::is_borrowing)
+    }
+
+    // `let source_ident = source;`, mutable when entries are removed from the map
+    pub fn source_binding(&self) -> TokenStream {
+        let source = &self.source;
+        let source_ident = &self.source_ident;
+        let is_mut = self.removes_entries().then(<Token![mut]>::default);
+        quote! { let #is_mut #source_ident = #source; }
+    }
+
+    // Map lists look up the key by name, which gives a place expression that modifiers can
+    // borrow or clone like a field. Other entries are removed from an owned map, or copied out of
+    // a borrowed one.
+    fn access(&self, field: &Field, source: &syn::Ident) -> TokenStream {
+        if self.map.is_none() {
+            return field.access(source);
+        }
+
+        let source = syn::Ident::new(&source.to_string(), field.name.span());
+        let key = field
+            .member
+            .as_ref()
+            .unwrap_or(&field.name)
+            .unraw()
+            .to_string();
+        let expect = format!("missing key `{key}` in map spread list");
+
+        if field.is_borrowing() {
+            quote_spanned! { field.name.span()=> (* #source . get(#key) . expect(#expect)) }
+        } else if self.is_owned_map() {
+            quote_spanned! { field.name.span()=> #source . remove(#key) . expect(#expect) }
+        } else {
+            quote_spanned! { field.name.span()=> {
+                #[diagnostic::on_unimplemented(
+                    message = "cannot move `{Self}` out of a borrowed map in a map spread list",
+                    note = "clone the entry with `+field`, or spread from an owned map to remove it"
+                )]
+                trait __MapEntry: ::core::marker::Copy {}
+                impl<T: ::core::marker::Copy> __MapEntry for T {}
+
+                fn __copy<T: __MapEntry>(value: &T) -> T {
+                    *value
+                }
+
+                __copy(#source . get(#key) . expect(#expect))
+            }}
+        }
+    }
+
     fn field_expansion(&self) -> TokenStream {
         let source = &self.source_ident;
-        let fields = self.fields_list.iter().map(|field| {
-            let name = &field.name;
-            field.field_expansion(quote! { #source . #name })
-        });
+        let fields = self
+            .fields_list
+            .iter()
+            .map(|field| field.field_expansion(self.access(field, source)));
 
         quote! { #( #fields ),* }
     }
 
+    fn values_expansion(&self) -> Vec<(&syn::Ident, TokenStream)> {
+        let source = &self.source_ident;
+        self.fields_list
+            .iter()
+            .map(|field| {
+                let value = field.value_with_modifiers(self.access(field, source));
+                (&field.name, value)
+            })
+            .collect()
+    }
+
     fn let_expansion(&self) -> TokenStream {
+        // The source is borrowed when no field needs to consume it, so it can still be used
         let source = &self.source;
-        let fields = self.fields_list.iter().map(|field| {
-            let name = &field.name;
-            field.value_with_modifiers(quote! { __source . #name })
-        });
+        let source = if self.fields_list.iter().all(Field::is_borrowing) {
+            quote! { & #source }
+        } else {
+            quote! { #source }
+        };
+        let source_ident = syn::Ident::new("__source", Span::call_site());
+        let is_mut = self.removes_entries().then(<Token![mut]>::default);
+        let fields = self
+            .fields_list
+            .iter()
+            .map(|field| field.value_with_modifiers_untyped(self.access(field, &source_ident)));
         let fields_mut = self.fields_list.iter().map(|field| &field.is_mut);
         let fields_name = self.fields_list.iter().map(|field| &field.name);
+        let fields_type = self.fields_list.iter().map(|field| match &field.ty {
+            Some(ty) => quote! { #ty },
+            None => quote! { _ },
+        });
 
         quote! {
             let (
                 #( #fields_mut #fields_name , )*
-            ) = {
-                let __source = #source;
+            ): ( #( #fields_type , )* ) = {
+                let #is_mut #source_ident = #source;
                 ( #( #fields , )* )
             };
         }
@@ -0,0 +1,91 @@
+use super::*;
+
+pub fn compare_fields(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(tokens as syn::DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: syn::DeriveInput) -> syn::Result<TokenStream> {
+    let syn::Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "`CompareFields` can only be derived on structs",
+        ));
+    };
+
+    // Fields with `#[compare_fields(skip)]` are never compared, and don't need to implement
+    // `PartialEq` and `Debug`
+    let mut compared = vec![];
+    for (i, field) in data.fields.iter().enumerate() {
+        let mut skip = false;
+        for attr in &field.attrs {
+            if attr.path().is_ident("compare_fields") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("skip") {
+                        skip = true;
+                        Ok(())
+                    } else {
+                        Err(meta.error("expected `skip`"))
+                    }
+                })?;
+            }
+        }
+
+        if !skip {
+            compared.push((i, field));
+        }
+    }
+
+    // Tuple fields are named after their index (`0`, `1`, ..)
+    let members: Vec<syn::Member> = compared
+        .iter()
+        .map(|(i, field)| match &field.ident {
+            Some(name) => syn::Member::Named(name.clone()),
+            None => syn::Member::Unnamed(syn::Index::from(*i)),
+        })
+        .collect();
+    let names: Vec<_> = members.iter().map(member_name).collect();
+    let types: Vec<_> = compared.iter().map(|(_, field)| &field.ty).collect();
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // Used by the `except [..]` form of `assert_fields_eq!` and similar macros, which don't know
+    // the fields of the compared values.
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #[doc(hidden)]
+            #[allow(dead_code)]
+            pub fn __spread_macros_mismatches(
+                &self,
+                other: &Self,
+                except: &[&str],
+            ) -> ::std::vec::Vec<::std::string::String>
+            where
+                #( #types: ::core::cmp::PartialEq + ::core::fmt::Debug, )*
+            {
+                let mut mismatches = ::std::vec::Vec::new();
+                #(
+                    if !except.contains(&#names) && self.#members != other.#members {
+                        mismatches.push(::std::format!(
+                            "  `{}`: left: {:?}, right: {:?}",
+                            #names,
+                            self.#members,
+                            other.#members,
+                        ));
+                    }
+                )*
+                mismatches
+            }
+        }
+    })
+}
+
+pub fn member_name(member: &syn::Member) -> String {
+    match member {
+        syn::Member::Named(name) => name.to_string(),
+        syn::Member::Unnamed(index) => index.index.to_string(),
+    }
+}
@@ -0,0 +1,32 @@
+use super::{common::*, *};
+
+pub fn deanon(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let DeAnon { list } = parse_macro_input!(tokens as DeAnon);
+
+    SpreadItem::SpreadList(list).let_expansion().into()
+}
+
+struct DeAnon {
+    list: SpreadList,
+}
+
+impl Parse for DeAnon {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let value: syn::Expr = input.parse()?;
+        let _: Token![=>] = input.parse()?;
+        let fields: proc_macro2::TokenTree = input.parse()?;
+        let rename: TokenStream = input.parse()?;
+
+        // `value => { .. }` is the same as the spread list `{ .. } in value`
+        let list: SpreadList = syn::parse2(quote! { #fields in ( #value ) #rename })?;
+
+        if list.fields_list.is_empty() {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "Must list at least one field",
+            ));
+        }
+
+        Ok(Self { list })
+    }
+}
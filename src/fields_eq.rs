@@ -0,0 +1,38 @@
+use crate::{assert_fields_eq::AssertFieldsEq, *};
+
+pub fn fields_eq(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let fields_eq = parse_macro_input!(tokens as AssertFieldsEq);
+
+    if let Some(all) = &fields_eq.all {
+        return syn::Error::new(all.span(), "`all` is only allowed in `assert_fields_ne!`")
+            .into_compile_error()
+            .into();
+    }
+    if let Some(token) = fields_eq.fmt_args.clone().into_iter().next() {
+        return syn::Error::new(token.span(), "`fields_eq!` doesn't accept a message")
+            .into_compile_error()
+            .into();
+    }
+
+    if let Some(mismatches) = fields_eq.expand_except() {
+        return quote! {
+            {
+                #mismatches
+
+                mismatches.is_empty()
+            }
+        }
+        .into();
+    }
+
+    let (_, bindings) = fields_eq.expand_bindings();
+
+    quote! {
+        {
+            #bindings
+
+            left == right
+        }
+    }
+    .into()
+}
@@ -1,20 +1,79 @@
 use {
     super::{common::*, *},
     std::collections::VecDeque,
-    syn::{ext::IdentExt, parenthesized},
+    syn::{bracketed, ext::IdentExt, parenthesized},
 };
 
 pub fn fn_struct(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let list = parse_macro_input!(tokens as FnStructList);
 
-    let expanded = list.0.into_iter().map(FnStruct::expand);
+    let structs: Vec<_> = list.0.into_iter().collect();
+    let conversions = conversions(&structs);
+    let expanded = structs.into_iter().map(FnStruct::expand);
 
     quote! {
         #( #expanded )*
+        #( #conversions )*
     }
     .into()
 }
 
+// `From` impls between structs of the same invocation when the fields of one are a subset of the
+// fields of the other, either dropping the additional fields or taking them from `Default`.
+// Generic structs are not supported.
+fn conversions(structs: &[FnStruct]) -> Vec<TokenStream> {
+    let fields_of = |fn_struct: &FnStruct| -> Vec<(String, String)> {
+        fn_struct
+            .fields
+            .iter()
+            .map(|field| {
+                let type_ = &field.type_;
+                (field.name.to_string(), quote! { #type_ }.to_string())
+            })
+            .collect()
+    };
+    let is_subset =
+        |a: &[(String, String)], b: &[(String, String)]| a.iter().all(|f| b.contains(f));
+
+    let mut conversions = vec![];
+    for from in structs.iter().filter(|s| s.struct_gen.params.is_empty()) {
+        for into in structs.iter().filter(|s| s.struct_gen.params.is_empty()) {
+            if from.struct_name == into.struct_name {
+                continue;
+            }
+
+            let from_fields = fields_of(from);
+            let into_fields = fields_of(into);
+            let rest = if is_subset(&into_fields, &from_fields) {
+                None
+            } else if into.impl_default && is_subset(&from_fields, &into_fields) {
+                Some(quote! { ..::core::default::Default::default() })
+            } else {
+                continue;
+            };
+
+            let from_name = &from.struct_name;
+            let into_name = &into.struct_name;
+            let names = into
+                .fields
+                .iter()
+                .map(|field| &field.name)
+                .filter(|name| from.fields.iter().any(|field| &field.name == *name));
+            conversions.push(quote! {
+                impl ::core::convert::From<#from_name> for #into_name {
+                    fn from(value: #from_name) -> Self {
+                        Self {
+                            #( #names: value.#names, )*
+                            #rest
+                        }
+                    }
+                }
+            });
+        }
+    }
+    conversions
+}
+
 struct FnStructList(Punctuated<FnStruct, Token![;]>);
 
 impl Parse for FnStructList {
@@ -23,65 +82,289 @@ impl Parse for FnStructList {
     }
 }
 
-struct FnStruct {
+pub struct FnStruct {
     struct_attr: Vec<syn::Attribute>,
+    options: FnStructOptions,
     vis: syn::Visibility,
     call_by_ref: Option<Token![&]>,
     struct_name: syn::Ident,
     struct_gen: syn::Generics,
     call_gen: syn::Generics,
+    // Some = `for const fn`, `call` is a const fn
+    constness: Option<Token![const]>,
+    // Some = `for async fn`, `call` is async and awaits the function
+    asyncness: Option<Token![async]>,
+    // Some = `for unsafe fn` (like functions from `extern` blocks), `call` is unsafe
+    unsafety: Option<Token![unsafe]>,
+    // Some = `for extern "ABI" fn`, only used to check the signature of the function
+    abi: Option<syn::Abi>,
     fn_path: syn::ExprPath,
     fields: VecDeque<TypedField>,
+    // Index in `fields` of each argument of the function (after `self`)
+    args_order: Vec<usize>,
+    // `@call` arguments with their position in the arguments of the function (after `self`)
+    call_args: Vec<(usize, TypedField)>,
     return_type: Option<syn::Type>,
+    // Some = `-> Result<T, E> map_err Target`, `try_call` returns `Result<T, Target>`
+    map_err: Option<syn::Type>,
     self_: Option<TypedField>,
+    // true = `[path] self: Type`, the first field is a receiver obtained through a guard
+    guarded_receiver: bool,
     impl_default: bool,
 }
 
 impl FnStruct {
-    fn expand(self) -> TokenStream {
+    pub fn expand(self) -> TokenStream {
         let FnStruct {
             struct_attr,
+            options,
             vis,
             call_by_ref,
             struct_name,
             struct_gen,
             call_gen,
+            constness,
+            asyncness,
+            unsafety,
+            abi,
             fn_path,
             fields,
+            args_order,
+            call_args,
             return_type,
+            map_err,
             self_,
+            guarded_receiver,
             impl_default,
         } = self;
 
         let (struct_impl_gen, struct_ty_gen, struct_where) = struct_gen.split_for_impl();
+
+        // Structs are declared without bounds, which are only required by the impls, but keep
+        // const generics and default types
+        let mut struct_decl_gen = struct_gen.clone();
+        struct_decl_gen.where_clause = None;
+        for param in &mut struct_decl_gen.params {
+            match param {
+                syn::GenericParam::Lifetime(param) => {
+                    param.bounds.clear();
+                    param.colon_token = None;
+                }
+                syn::GenericParam::Type(param) => {
+                    param.bounds.clear();
+                    param.colon_token = None;
+                }
+                syn::GenericParam::Const(_) => (),
+            }
+        }
         let (call_impl_gen, _call_ty_gen, call_where) = call_gen.split_for_impl();
 
         let fields_name: Vec<_> = fields.iter().map(|field| &field.name).collect();
         let fields_type: Vec<_> = fields.iter().map(|field| &field.type_).collect();
-        let fields_value: Vec<_> = fields
+        let fields_attrs: Vec<_> = fields.iter().map(|field| &field.attrs).collect();
+        let fields_vis: Vec<_> = fields.iter().map(|field| &field.vis).collect();
+        // Calls variants, the first one being used by the other methods
+        let calls: Vec<(syn::Ident, CallReceiver)> = if options.calls.is_empty() {
+            let receiver = match call_by_ref {
+                Some(_) => CallReceiver::Ref,
+                None => CallReceiver::Value,
+            };
+            vec![(syn::Ident::new("call", struct_name.span()), receiver)]
+        } else {
+            options
+                .calls
+                .iter()
+                .map(|&receiver| {
+                    let name = match receiver {
+                        CallReceiver::Value => "call",
+                        CallReceiver::Ref => "call_ref",
+                        CallReceiver::Mut => "call_mut",
+                    };
+                    (syn::Ident::new(name, struct_name.span()), receiver)
+                })
+                .collect()
+        };
+        let (call_ident, call_receiver) = calls[0].clone();
+
+        let fields_value = |receiver: CallReceiver| -> Vec<TokenStream> {
+            fields
+                .iter()
+                .map(|field| {
+                    let source = field.name.clone();
+                    let source = match &field.fallback {
+                        // Calls by reference can't move the `Option` out of `self`
+                        Some(fallback) if receiver != CallReceiver::Value => quote! {
+                            ::core::option::Option::unwrap_or_else(
+                                ::core::clone::Clone::clone(&self.#source),
+                                || #fallback,
+                            )
+                        },
+                        Some(fallback) => quote! {
+                            ::core::option::Option::unwrap_or_else(self.#source, || #fallback)
+                        },
+                        None => quote! { self . #source },
+                    };
+                    Field::from(field.clone()).value_with_modifiers(source)
+                })
+                .enumerate()
+                // Guards are dereferenced to `&T` or `&mut T` by coercion
+                .map(|(i, value)| match i {
+                    0 if guarded_receiver => quote! { &mut #value },
+                    _ => value,
+                })
+                .collect()
+        };
+        let args_name: Vec<_> = args_order.iter().map(|&i| &fields[i].name).collect();
+        let args_type: Vec<_> = args_order.iter().map(|&i| &fields[i].type_).collect();
+
+        // Chainable `with_<field>` setters
+        let fields_setter: Vec<_> = fields
             .iter()
             .map(|field| {
-                let source = field.name.clone();
-                Field::from(field.clone()).value_with_modifiers(quote! { self . #source })
+                syn::Ident::new(&format!("with_{}", field.name.unraw()), field.name.span())
             })
             .collect();
 
+        // Patch struct with optional fields used by `call_with` to override some fields
+        let patch_name = syn::Ident::new(&format!("{struct_name}Patch"), struct_name.span());
+
+        // Generated documentation, unless the struct is already documented
+        let fn_path_doc = doc_string(quote! { #fn_path });
+        let struct_doc = if struct_attr.iter().any(|attr| attr.path().is_ident("doc")) {
+            vec![]
+        } else {
+            let mut lines = vec![format!(" Arguments for a call to `{fn_path_doc}`.")];
+
+            if impl_default {
+                lines.push(String::new());
+                lines.push(" Default values:".to_string());
+                for field in &fields {
+                    let value = match (&field.value, &field.fallback) {
+                        (Some(value), _) => doc_string(quote! { #value }),
+                        (None, Some(fallback)) => {
+                            format!(
+                                "None` (falls back to `{}`)",
+                                doc_string(quote! { #fallback })
+                            )
+                        }
+                        (None, None) => "Default::default()".to_string(),
+                    };
+                    lines.push(format!(" - `{}`: `{value}`", field.name));
+                }
+            }
+
+            let init = if impl_default {
+                format!("{struct_name}::default()")
+            } else {
+                let names: Vec<_> = fields.iter().map(|field| field.name.to_string()).collect();
+                format!("{struct_name} {{ {} }}", names.join(", "))
+            };
+            let receiver = self_
+                .iter()
+                .map(|_| "receiver".to_string())
+                .chain(call_args.iter().map(|(_, field)| field.name.to_string()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            lines.extend([
+                String::new(),
+                " # Example".to_string(),
+                " ```ignore".to_string(),
+                format!(" let res = {init}.{call_ident}({receiver});"),
+                " ```".to_string(),
+            ]);
+
+            lines
+        };
+        let call_doc = format!(" Calls `{fn_path_doc}` with the fields of this struct.");
+        let call_with_doc =
+            format!(" Overrides the fields set in `patch`, then calls `{fn_path_doc}`.");
+        let try_call_doc = format!(" Calls `{fn_path_doc}`, converting the error with `Into`.");
+        let patch_doc =
+            format!(" Optional overrides of the fields of [`{struct_name}`], used by `call_with`.");
+        // Metadata about the function
+        let arg_names: Vec<_> = args_name
+            .iter()
+            .map(|name| name.unraw().to_string())
+            .collect();
+        let arg_count = arg_names.len();
+        let arg_names_doc = format!(
+            " Names of the fields passed to `{fn_path_doc}`, in the order of its parameters."
+        );
+        let arg_count_doc = format!(" Number of fields passed to `{fn_path_doc}`.");
+        let fn_path_const_doc = " Path of the called function.";
+        let fields_setter_doc: Vec<_> = fields
+            .iter()
+            .map(|field| format!(" Sets `{}`.", field.name))
+            .collect();
+
+        // `Result` return types get a `try_call` converting the error with `Into`
+        let result_types = return_type.as_ref().and_then(result_types);
+
         // We generate `-> ()` so that error message can provided expected type
+        let output_type = return_type.clone();
+        let output = match &output_type {
+            Some(rt) => quote! { #rt },
+            None => quote! { () },
+        };
         let return_type = if let Some(rt) = return_type {
             quote! { -> #rt }
         } else {
             quote! { -> () }
         };
 
+        // `new` takes the fields without default value, which allows to add fields with default
+        // values to `#[non_exhaustive]` structs without breaking callers
+        let new_fields: Vec<_> = fields
+            .iter()
+            .filter(|field| field.value.is_none() && field.fallback.is_none())
+            .collect();
+        let new_args_name: Vec<_> = new_fields.iter().map(|field| &field.name).collect();
+        let new_args_type: Vec<_> = new_fields.iter().map(|field| &field.type_).collect();
+        // Default values are bound in declaration order, so that they can use previous fields
+        let default_let = |field: &TypedField| {
+            let TypedField { name, type_, .. } = field;
+            match (&field.value, &field.fallback) {
+                (Some(value), _) => Some(quote! { let #name: #type_ = #value; }),
+                (None, Some(_)) => {
+                    Some(quote! { let #name: #type_ = ::core::option::Option::None; })
+                }
+                (None, None) => None,
+            }
+        };
+        let new_lets: Vec<_> = fields.iter().filter_map(default_let).collect();
+        let new_doc = if new_fields.len() == fields.len() {
+            " Creates the struct from its fields.".to_string()
+        } else {
+            " Creates the struct from the fields without default value, the other fields having \
+             their default value."
+                .to_string()
+        };
+        let non_exhaustive = struct_attr
+            .iter()
+            .any(|attr| attr.path().is_ident("non_exhaustive"));
+        let new = (options.new.is_some() || non_exhaustive).then(|| {
+            quote! {
+                #[doc = #new_doc]
+                pub fn new( #( #new_args_name: #new_args_type ),* ) -> Self {
+                    #( #new_lets )*
+                    Self { #( #fields_name ),* }
+                }
+            }
+        });
+
         let impl_default = if impl_default {
-            let fields_default_value: Vec<_> = fields.iter().map(|field| &field.value).collect();
+            let default_lets = fields.iter().map(|field| {
+                default_let(field).unwrap_or_else(|| {
+                    let TypedField { name, type_, .. } = field;
+                    quote! { let #name: #type_ = ::core::default::Default::default(); }
+                })
+            });
 
             Some(quote! {
                 impl #struct_impl_gen ::core::default::Default for #struct_name #struct_ty_gen #struct_where {
                     fn default() -> Self {
-                        Self {
-                            #( #fields_name: #fields_default_value ),*
-                        }
+                        #( #default_lets )*
+                        Self { #( #fields_name ),* }
                     }
                 }
             })
@@ -89,7 +372,13 @@ impl FnStruct {
             None
         };
 
-        let (self_in_arg, self_out_arg) = if let Some(TypedField { modifier, name, .. }) = &self_ {
+        let (self_in_arg, self_out_arg, self_arg_type) = if let Some(TypedField {
+            modifier,
+            name,
+            type_,
+            ..
+        }) = &self_
+        {
             let modifier = match modifier {
                 Some(SpreadModifier::Ref(token_ref)) => quote! { #token_ref },
                 Some(SpreadModifier::RefMut(token_ref, token_mut)) => {
@@ -109,11 +398,8 @@ impl FnStruct {
 
             // Fully Qualified Path `<T as Trait>::Item`, we need to turn it into just
             // `T`.
-            if let Some(syn::QSelf { ty, .. }) = &self_type.qself {
-                (
-                    Some(quote! { __self: #modifier #ty , }),
-                    Some(quote! { __self, }),
-                )
+            let self_type = if let Some(syn::QSelf { ty, .. }) = &self_type.qself {
+                quote! { #ty }
             }
             // Otherwise this is a normal path to a method in a type, so we simply have
             // to remove the last item: the method part.
@@ -129,27 +415,628 @@ impl FnStruct {
                 }
                 self_type.path.segments.pop_punct();
 
+                quote! { #self_type }
+            };
+
+            // `self: Arc<Self>`-like receivers, `Self` being replaced by the type of the method
+            let self_type = match type_ {
+                Some(type_) => replace_self(quote! { #type_ }, &self_type),
+                None => quote! { #modifier #self_type },
+            };
+
+            (
+                Some(quote! { __self: #self_type , }),
+                Some(quote! { __self, }),
+                Some(quote! { #self_type , }),
+            )
+        } else {
+            (None, None, None)
+        };
+
+        // `@call` arguments follow `self` in the parameters of `call`, but are passed to the
+        // function at their position
+        let call_args_name: Vec<_> = call_args.iter().map(|(_, field)| &field.name).collect();
+        let call_args_type: Vec<_> = call_args.iter().map(|(_, field)| &field.type_).collect();
+        let call_args_value: Vec<_> = call_args
+            .iter()
+            .map(|(i, field)| {
+                let name = &field.name;
                 (
-                    Some(quote! { __self: #modifier #self_type , }),
-                    Some(quote! { __self, }),
+                    *i,
+                    Field::from(field.clone()).value_with_modifiers(quote! { #name }),
                 )
+            })
+            .collect();
+        let self_target_arg = self_out_arg.clone();
+        let self_in_arg = quote! { #self_in_arg #( #call_args_name: #call_args_type, )* };
+        let self_out_arg = quote! { #self_out_arg #( #call_args_name, )* };
+
+        // Coerces the function to a function pointer with the expected signature, so that a
+        // change of signature is reported on the path to the function. Fields must have the
+        // exact types of the parameters, as there is no coercion of the arguments.
+        let check_signature = options.check_signature.as_ref().map(|_| {
+            let params_type = interleave(
+                args_type.iter().map(|type_| quote! { #type_ }),
+                call_args
+                    .iter()
+                    .map(|(i, field)| {
+                        let type_ = &field.type_;
+                        (*i, quote! { #type_ })
+                    }),
+            );
+            quote_spanned! { fn_path.span()=>
+                #[doc(hidden)]
+                #[allow(dead_code)]
+                fn __check_signature #call_impl_gen () #call_where {
+                    let _: #unsafety #abi fn( #self_arg_type #( #params_type ),* ) #return_type = #fn_path;
+                }
             }
-        } else {
-            (None, None)
+        });
+
+        let await_ = asyncness.map(|_| quote! { .await });
+
+        let in_unsafe = |tokens: TokenStream| match unsafety {
+            Some(token_unsafe) => quote! { #token_unsafe { #tokens } },
+            None => tokens,
         };
+        let fn_call = |receiver: CallReceiver| {
+            let fields_value = fields_value(receiver);
+            let args_value = interleave(
+                args_order.iter().map(|&i| fields_value[i].clone()),
+                call_args_value.iter().cloned(),
+            );
+
+            match &options.instrument {
+                None => {
+                    in_unsafe(quote! { #fn_path ( #self_target_arg #( #args_value ),*  ) #await_ })
+                }
+                // Field values are recorded before they are moved into the function
+                Some((instrument, skip)) => {
+                    let recorded = fields
+                        .iter()
+                        .map(|field| &field.name)
+                        .filter(|name| !skip.contains(name));
+                    let span = quote_spanned! { instrument.span()=>
+                        ::tracing::span!(
+                            ::tracing::Level::INFO,
+                            #fn_path_doc,
+                            #( #recorded = ?self.#recorded ),*
+                        )
+                    };
+                    let fn_call =
+                        in_unsafe(quote! { #fn_path ( #self_target_arg #( #args_value ),*  ) });
+
+                    match asyncness {
+                        Some(_) => quote! {
+                            ::tracing::Instrument::instrument(#fn_call, #span).await
+                        },
+                        None => quote! {
+                            let __span = #span;
+                            let __enter = __span.enter();
+                            #fn_call
+                        },
+                    }
+                }
+            }
+        };
+        let self_call = in_unsafe(quote! { self.#call_ident( #self_out_arg ) #await_ });
+
+        // Fields passed by `&mut` require a mutable `self` when called by value
+        let has_ref_mut = fields.iter().any(|field| {
+            matches!(
+                field.modifier,
+                Some(SpreadModifier::RefMut(_, _) | SpreadModifier::CustomRefMut(_, _, _))
+            )
+        });
+        let receiver_tokens = |receiver: CallReceiver| match receiver {
+            CallReceiver::Value if has_ref_mut => quote! { mut self },
+            CallReceiver::Value => quote! { self },
+            CallReceiver::Ref => quote! { &self },
+            CallReceiver::Mut => quote! { &mut self },
+        };
+        let call_self = receiver_tokens(call_receiver);
+        // Receiver of methods forwarding to the main call variant
+        let forward_self = match call_receiver {
+            CallReceiver::Value => quote! { self },
+            receiver => receiver_tokens(receiver),
+        };
+
+        // Retries of `&Name` structs, as other structs are consumed by the first call
+        let call_with_retry = result_types
+            .as_ref()
+            .filter(|_| {
+                call_receiver == CallReceiver::Ref
+                    && self_in_arg.is_empty()
+                    && asyncness.is_none()
+            })
+            .map(|(ok, err)| {
+                let mut retry_gen = call_gen.clone();
+                retry_gen.params.push(syn::parse_quote!(
+                    __P: ::core::ops::FnMut(u32, &#err) -> bool
+                ));
+                let (retry_impl_gen, _retry_ty_gen, retry_where) = retry_gen.split_for_impl();
+                let call = in_unsafe(quote! { self.#call_ident() });
+                let call_with_retry_doc = format!(
+                    " Calls `{fn_path_doc}` until it succeeds or `policy` returns `false`. `policy` \
+                     is called with the number of failed attempts and the last error, and can be \
+                     used to wait between attempts."
+                );
+
+                quote! {
+                    #[doc = #call_with_retry_doc]
+                    pub #unsafety fn call_with_retry #retry_impl_gen (&self, mut policy: __P)
+                        -> ::core::result::Result<#ok, #err> #retry_where {
+                        let mut attempts = 0;
+                        loop {
+                            match #call {
+                                ::core::result::Result::Err(err) => {
+                                    attempts += 1;
+                                    if !policy(attempts, &err) {
+                                        return ::core::result::Result::Err(err);
+                                    }
+                                }
+                                ok => return ok,
+                            }
+                        }
+                    }
+                }
+            });
+
+        let try_call = result_types.map(|(ok, err)| {
+            // Without `map_err` the target error type is chosen by the caller
+            let (target, try_gen) = match map_err {
+                Some(target) => {
+                    let mut try_gen = call_gen.clone();
+                    try_gen
+                        .make_where_clause()
+                        .predicates
+                        .push(syn::parse_quote!(#err: ::core::convert::Into<#target>));
+                    (target, try_gen)
+                }
+                None => {
+                    let mut try_gen = call_gen.clone();
+                    try_gen.params.push(syn::parse_quote!(__E));
+                    try_gen
+                        .make_where_clause()
+                        .predicates
+                        .push(syn::parse_quote!(#err: ::core::convert::Into<__E>));
+                    (syn::parse_quote!(__E), try_gen)
+                }
+            };
+            let (try_impl_gen, _try_ty_gen, try_where) = try_gen.split_for_impl();
+
+            quote! {
+                #[doc = #try_call_doc]
+                pub #asyncness #unsafety fn try_call #try_impl_gen ( #forward_self, #self_in_arg)
+                    -> ::core::result::Result<#ok, #target> #try_where {
+                    ::core::result::Result::map_err(
+                        #self_call,
+                        ::core::convert::Into::into,
+                    )
+                }
+            }
+        });
+
+        let impls = options.impls.iter().map(|path| {
+            let fn_call = fn_call(call_receiver);
+            quote! {
+                impl #struct_impl_gen #path for #struct_name #struct_ty_gen #struct_where {
+                    type Output = #output;
+
+                    #asyncness #unsafety fn call( #call_self ) -> Self::Output {
+                        #fn_call
+                    }
+                }
+            }
+        });
+
+        // Closure traits on nightly, only for calls without parameters
+        let fn_traits = (cfg!(feature = "nightly")
+            && self_in_arg.is_empty()
+            && call_gen.params.is_empty()
+            && asyncness.is_none()
+            && unsafety.is_none())
+        .then(|| {
+            let (once_self, once_arg) = match call_receiver {
+                CallReceiver::Value => (quote! { self }, quote! { self }),
+                CallReceiver::Ref => (quote! { self }, quote! { &self }),
+                CallReceiver::Mut => (quote! { mut self }, quote! { &mut self }),
+            };
+            let fn_once = quote! {
+                impl #struct_impl_gen ::core::ops::FnOnce<()> for #struct_name #struct_ty_gen #struct_where {
+                    type Output = #output;
+
+                    extern "rust-call" fn call_once(#once_self, _: ()) -> Self::Output {
+                        #struct_name::#call_ident(#once_arg)
+                    }
+                }
+            };
+            let fn_mut = quote! {
+                impl #struct_impl_gen ::core::ops::FnMut<()> for #struct_name #struct_ty_gen #struct_where {
+                    extern "rust-call" fn call_mut(&mut self, _: ()) -> Self::Output {
+                        #struct_name::#call_ident(self)
+                    }
+                }
+            };
+
+            match call_receiver {
+                CallReceiver::Value => fn_once,
+                CallReceiver::Mut => quote! { #fn_once #fn_mut },
+                CallReceiver::Ref => quote! {
+                    #fn_once
+                    #fn_mut
+
+                    impl #struct_impl_gen ::core::ops::Fn<()> for #struct_name #struct_ty_gen #struct_where {
+                        extern "rust-call" fn call(&self, _: ()) -> Self::Output {
+                            #struct_name::#call_ident(self)
+                        }
+                    }
+                },
+            }
+        });
+
+        // Requires `serde` with the `derive` feature in the calling crate
+        let serde_derive = options.serde.map(|serde| {
+            quote_spanned! { serde.span()=>
+                #[derive(::serde::Serialize, ::serde::Deserialize)]
+            }
+        });
+
+        let arbitrary = options.arbitrary.map(|_| {
+            let mut arbitrary_gen = struct_gen.clone();
+            let arbitrary_where = arbitrary_gen.make_where_clause();
+            for type_ in &fields_type {
+                arbitrary_where
+                    .predicates
+                    .push(syn::parse_quote!(#type_: ::proptest::arbitrary::Arbitrary + 'static));
+            }
+            let (_, _, arbitrary_where) = arbitrary_gen.split_for_impl();
+
+            // Nested pairs of strategies, which are not limited in size like tuples
+            let (strategy, pattern) = fields.iter().rev().fold(
+                (
+                    quote! { ::proptest::strategy::Just(()) },
+                    quote! { () },
+                ),
+                |(strategy, pattern), field| {
+                    let type_ = &field.type_;
+                    let name = &field.name;
+                    (
+                        quote! { (::proptest::arbitrary::any::<#type_>(), #strategy) },
+                        quote! { (#name, #pattern) },
+                    )
+                },
+            );
+
+            quote! {
+                impl #struct_impl_gen ::proptest::arbitrary::Arbitrary for #struct_name #struct_ty_gen #arbitrary_where {
+                    type Parameters = ();
+                    type Strategy = ::proptest::strategy::BoxedStrategy<Self>;
+
+                    fn arbitrary_with(_: ()) -> Self::Strategy {
+                        ::proptest::strategy::Strategy::boxed(
+                            ::proptest::strategy::Strategy::prop_map(
+                                #strategy,
+                                |#pattern| Self { #( #fields_name ),* },
+                            )
+                        )
+                    }
+                }
+            }
+        });
+
+        let cached = options.cached.map(|_| {
+            let call = in_unsafe(quote! { ::core::clone::Clone::clone(self).#call_ident() });
+
+            // Statics can't depend on generic parameters, so only the cache map can be provided
+            // for generic structs.
+            let call_cached = struct_gen.params.is_empty().then(|| {
+                let call_cached_doc =
+                    format!(" Calls `{fn_path_doc}`, memoizing results in a static cache.");
+                quote! {
+                    #[doc = #call_cached_doc]
+                    pub #unsafety fn call_cached(&self) -> #output
+                    where
+                        Self: ::core::hash::Hash + ::core::cmp::Eq + ::core::clone::Clone + ::core::marker::Send + 'static,
+                        #output: ::core::clone::Clone + ::core::marker::Send + 'static,
+                    {
+                        static CACHE: ::std::sync::OnceLock<
+                            ::std::sync::Mutex<::std::collections::HashMap<#struct_name, #output>>,
+                        > = ::std::sync::OnceLock::new();
+
+                        let cache = CACHE.get_or_init(::core::default::Default::default);
+                        let lock = || cache.lock().unwrap_or_else(::std::sync::PoisonError::into_inner);
+
+                        // The lock is not held while calling to allow recursive calls
+                        if let ::core::option::Option::Some(output) = lock().get(self) {
+                            return ::core::clone::Clone::clone(output);
+                        }
+                        let output = #call;
+                        lock().insert(::core::clone::Clone::clone(self), ::core::clone::Clone::clone(&output));
+                        output
+                    }
+                }
+            });
+            let call_cached_in_doc =
+                format!(" Calls `{fn_path_doc}`, memoizing results in the provided `cache`.");
+
+            quote! {
+                #call_cached
+
+                #[doc = #call_cached_in_doc]
+                pub #unsafety fn call_cached_in(
+                    &self,
+                    cache: &mut ::std::collections::HashMap<Self, #output>,
+                ) -> #output
+                where
+                    Self: ::core::hash::Hash + ::core::cmp::Eq + ::core::clone::Clone,
+                    #output: ::core::clone::Clone,
+                {
+                    if let ::core::option::Option::Some(output) = cache.get(self) {
+                        return ::core::clone::Clone::clone(output);
+                    }
+                    let output = #call;
+                    cache.insert(::core::clone::Clone::clone(self), ::core::clone::Clone::clone(&output));
+                    output
+                }
+            }
+        });
+
+        let spawn = options.spawn.map(|_| {
+            let mut spawn_gen = call_gen.clone();
+            let spawn_where = spawn_gen.make_where_clause();
+            spawn_where.predicates.push(syn::parse_quote!(Self: ::core::marker::Send + 'static));
+            spawn_where.predicates.push(syn::parse_quote!(#output: ::core::marker::Send + 'static));
+            let (_, _, spawn_where) = spawn_gen.split_for_impl();
+            // The struct is moved into the task, and must be mutable to be called by `&mut`
+            let spawn_self = match call_receiver {
+                CallReceiver::Mut => quote! { mut self },
+                _ => quote! { self },
+            };
+
+            if asyncness.is_some() {
+                let spawn_doc = format!(" Spawns a `tokio` task calling `{fn_path_doc}`.");
+                quote! {
+                    #[doc = #spawn_doc]
+                    pub fn spawn #call_impl_gen (#spawn_self, #self_in_arg) -> ::tokio::task::JoinHandle<#output> #spawn_where {
+                        ::tokio::spawn(async move { self.#call_ident( #self_out_arg ).await })
+                    }
+                }
+            } else {
+                let spawn_doc =
+                    format!(" Calls `{fn_path_doc}` on the blocking thread pool of `tokio`.");
+                quote! {
+                    #[doc = #spawn_doc]
+                    pub fn spawn_blocking #call_impl_gen (#spawn_self, #self_in_arg) -> ::tokio::task::JoinHandle<#output> #spawn_where {
+                        ::tokio::task::spawn_blocking(move || self.#call_ident( #self_out_arg ))
+                    }
+                }
+            }
+        });
+
+        let validations: Vec<_> = fields
+            .iter()
+            .flat_map(|field| {
+                let name = &field.name;
+                field.validations.iter().map(move |Validation { predicate, message }| {
+                    let message = match message {
+                        Some(message) => format!("`{name}`: {}", message.value()),
+                        None => format!("`{name}` is invalid"),
+                    };
+                    quote_spanned! { predicate.span()=>
+                        if !__validate(&self.#name, #predicate) {
+                            return ::core::result::Result::Err(::std::string::String::from(#message));
+                        }
+                    }
+                })
+            })
+            .collect();
+        let validate = (!validations.is_empty()).then(|| {
+            let validate_doc = " Checks the `#[validate]` predicates of the fields, returning the \
+                                message of the first failing one.";
+            let call_validated_doc =
+                format!(" Calls `{fn_path_doc}` if `validate` succeeds, returns its error otherwise.");
+
+            quote! {
+                #[doc = #validate_doc]
+                pub fn validate(&self) -> ::core::result::Result<(), ::std::string::String> {
+                    // Provides the expected type of closures
+                    fn __validate<T: ?Sized>(value: &T, predicate: impl FnOnce(&T) -> bool) -> bool {
+                        predicate(value)
+                    }
+
+                    #( #validations )*
+                    ::core::result::Result::Ok(())
+                }
+
+                #[doc = #call_validated_doc]
+                pub #asyncness #unsafety fn call_validated #call_impl_gen ( #forward_self, #self_in_arg)
+                    -> ::core::result::Result<#output, ::std::string::String> #call_where {
+                    self.validate()?;
+                    ::core::result::Result::Ok(#self_call)
+                }
+            }
+        });
+
+        // Concrete fields must implement `Debug`, while generic ones are bounded by the method
+        let display_bounds = struct_gen.type_params().map(|param| &param.ident);
+        let display_call = options.display_call.as_ref().map(|_| {
+            let display_call_doc = format!(
+                " Renders the call to `{fn_path_doc}` with the `Debug` representation of the \
+                 fields, without performing it."
+            );
+            let display_self = self_
+                .as_ref()
+                .map(|_| quote! { ::std::string::String::from("self"), });
+            let display_path = format!("{fn_path_doc}(");
+            // `@call` arguments are rendered with their name
+            let display_args = interleave(
+                args_order.iter().map(|&i| {
+                    let TypedField { name, type_, .. } = &fields[i];
+                    quote! { __debug::<#type_>(&self.#name) }
+                }),
+                call_args.iter().map(|(i, field)| {
+                    let name = field.name.unraw().to_string();
+                    (*i, quote! { ::std::string::String::from(#name) })
+                }),
+            );
+
+            quote! {
+                #[doc = #display_call_doc]
+                pub fn display_call(&self) -> ::std::string::String
+                where
+                    #( #display_bounds: ::core::fmt::Debug, )*
+                {
+                    fn __debug<T: ?Sized + ::core::fmt::Debug>(value: &T) -> ::std::string::String {
+                        ::std::format!("{:?}", value)
+                    }
+
+                    let args: &[::std::string::String] = &[
+                        #display_self
+                        #( #display_args ),*
+                    ];
+                    ::std::format!("{}{})", #display_path, args.join(", "))
+                }
+            }
+        });
+
+        let metadata = options.metadata.as_ref().map(|_| {
+            quote! {
+                #[doc = #arg_names_doc]
+                pub const ARG_NAMES: &'static [&'static str] = &[ #( #arg_names ),* ];
+
+                #[doc = #arg_count_doc]
+                pub const ARG_COUNT: usize = #arg_count;
+
+                #[doc = #fn_path_const_doc]
+                pub const FN_PATH: &'static str = #fn_path_doc;
+            }
+        });
+
+        let setters = options.setters.as_ref().map(|_| {
+            quote! {
+                #(
+                    #[doc = #fields_setter_doc]
+                    pub fn #fields_setter(mut self, value: #fields_type) -> Self {
+                        self.#fields_name = value;
+                        self
+                    }
+                )*
+            }
+        });
+
+        let tuple_conversions = options.tuple.as_ref().map(|_| {
+            quote! {
+                impl #struct_impl_gen ::core::convert::From<#struct_name #struct_ty_gen> for ( #( #fields_type, )* ) #struct_where {
+                    fn from(value: #struct_name #struct_ty_gen) -> Self {
+                        ( #( value.#fields_name, )* )
+                    }
+                }
+
+                impl #struct_impl_gen ::core::convert::From<( #( #fields_type, )* )> for #struct_name #struct_ty_gen #struct_where {
+                    fn from(( #( #fields_name, )* ): ( #( #fields_type, )* )) -> Self {
+                        Self { #( #fields_name ),* }
+                    }
+                }
+            }
+        });
+
+        let patch_struct = options.patch.as_ref().map(|_| {
+            quote! {
+                #[doc = #patch_doc]
+                #vis struct #patch_name #struct_decl_gen {
+                    #( #fields_vis #fields_name: ::core::option::Option<#fields_type> ),*
+                }
+
+                impl #struct_impl_gen ::core::default::Default for #patch_name #struct_ty_gen #struct_where {
+                    fn default() -> Self {
+                        Self {
+                            #( #fields_name: ::core::option::Option::None ),*
+                        }
+                    }
+                }
+            }
+        });
+        let call_with = options.patch.as_ref().map(|_| {
+            quote! {
+                #[doc = #call_with_doc]
+                pub #asyncness #unsafety fn call_with #call_impl_gen (
+                    mut self,
+                    patch: #patch_name #struct_ty_gen,
+                    #self_in_arg
+                ) #return_type #call_where {
+                    #(
+                        if let ::core::option::Option::Some(value) = patch.#fields_name {
+                            self.#fields_name = value;
+                        }
+                    )*
+                    #self_call
+                }
+            }
+        });
+
+        let call_attrs = &options.call_attrs;
+        let call_attrs = quote! { #( #[#call_attrs] )* };
+        let calls_ident: Vec<_> = calls.iter().map(|(ident, _)| ident).collect();
+        let calls_self: Vec<_> = calls
+            .iter()
+            .map(|&(_, receiver)| receiver_tokens(receiver))
+            .collect();
+        let calls_body: Vec<_> = calls
+            .iter()
+            .map(|&(_, receiver)| fn_call(receiver))
+            .collect();
 
         quote! {
             #( #struct_attr )*
-            #vis struct #struct_name #struct_ty_gen {
-                #( #fields_name: #fields_type ),*
+            #( #[doc = #struct_doc] )*
+            #serde_derive
+            #vis struct #struct_name #struct_decl_gen {
+                #( #( #fields_attrs )* #fields_vis #fields_name: #fields_type ),*
             }
 
             #impl_default
 
+            #( #impls )*
+
+            #fn_traits
+
+            #arbitrary
+
+            #tuple_conversions
+
+            #patch_struct
+
             impl #struct_impl_gen #struct_name #struct_ty_gen #struct_where {
-                pub fn call #call_impl_gen ( #call_by_ref self, #self_in_arg) #return_type #call_where {
-                    #fn_path ( #self_out_arg #( #fields_value ),*  )
-                }
+                #metadata
+
+                #check_signature
+
+                #new
+
+                #(
+                    #[doc = #call_doc]
+                    #call_attrs
+                    pub #constness #asyncness #unsafety fn #calls_ident #call_impl_gen ( #calls_self, #self_in_arg) #return_type #call_where {
+                        #calls_body
+                    }
+                )*
+
+                #call_with
+
+                #try_call
+
+                #display_call
+
+                #validate
+
+                #call_with_retry
+
+                #cached
+
+                #spawn
+
+                #setters
             }
         }
     }
@@ -157,13 +1044,21 @@ impl FnStruct {
 
 impl Parse for FnStruct {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let struct_attr = input.call(syn::Attribute::parse_outer)?;
+        let mut struct_attr = vec![];
+        let mut options = FnStructOptions::default();
+        for attr in input.call(syn::Attribute::parse_outer)? {
+            if attr.path().is_ident("fn_struct") {
+                attr.parse_args_with(|input: ParseStream| options.parse_list(input))?;
+            } else {
+                struct_attr.push(attr);
+            }
+        }
 
         let vis = input.parse()?;
         let _: Token![struct] = input.parse()?;
 
         let lookahead = input.lookahead1();
-        let call_by_ref = if lookahead.peek(Token![&]) {
+        let call_by_ref: Option<Token![&]> = if lookahead.peek(Token![&]) {
             Some(input.parse()?)
         } else {
             None
@@ -186,9 +1081,42 @@ impl Parse for FnStruct {
             call_gen.where_clause = Some(input.parse()?);
         }
 
+        let constness: Option<Token![const]> = input.parse()?;
+        let asyncness: Option<Token![async]> = input.parse()?;
+        if let (Some(_), Some(token_async)) = (constness, asyncness) {
+            return Err(syn::Error::new(
+                token_async.span(),
+                "functions can't be both `const` and `async`",
+            ));
+        }
+        let unsafety: Option<Token![unsafe]> = input.parse()?;
+        // The ABI doesn't matter to `call` which is a Rust function calling the target, but is
+        // part of the checked signature
+        let abi: Option<syn::Abi> = input.parse()?;
         let _: Token![fn] = input.parse()?;
 
-        let fn_path = input.parse()?;
+        let fn_path: syn::ExprPath = input.parse()?;
+
+        // `<T as Trait>::method` requires `T: Trait`, which is added to `call` to not have to repeat
+        // it, and allows the trait to be generic over parameters of the struct.
+        if let Some(syn::QSelf { ty, position, .. }) = &fn_path.qself {
+            if *position > 0 {
+                let trait_path = syn::Path {
+                    leading_colon: fn_path.path.leading_colon,
+                    segments: fn_path
+                        .path
+                        .segments
+                        .iter()
+                        .take(*position)
+                        .cloned()
+                        .collect(),
+                };
+                call_gen
+                    .make_where_clause()
+                    .predicates
+                    .push(syn::parse_quote!(#ty: #trait_path));
+            }
+        }
 
         let paren;
         parenthesized!(paren in input);
@@ -201,25 +1129,63 @@ impl Parse for FnStruct {
             None
         };
 
+        let map_err = if input.peek(syn::Ident) && input.fork().parse::<syn::Ident>()? == "map_err"
+        {
+            let map_err: syn::Ident = input.parse()?;
+            if return_type.as_ref().and_then(result_types).is_none() {
+                return Err(syn::Error::new(
+                    map_err.span(),
+                    "`map_err` requires the function to return a `Result<T, E>`",
+                ));
+            }
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
         let mut fields: VecDeque<_> =
             Punctuated::<TypedField, Token![,]>::parse_terminated(&paren)?
                 .into_iter()
                 .collect();
 
-        // Extract initial self if any.
-        let self_ = if let Some(first) = fields.front() {
-            if first.type_.is_none() {
-                fields.pop_front()
-            } else {
+        // Extract initial self if any. A typed `self: Type` is stored in the struct as a
+        // `receiver` field instead of being a parameter of `call`, unless its type mentions
+        // `Self` (`self: Arc<Self>`).
+        let mut guarded_receiver = false;
+        let self_ = match fields.front_mut() {
+            Some(first)
+                if first.name == "self"
+                    && first
+                        .type_
+                        .as_ref()
+                        .is_some_and(|type_| !contains_self(quote! { #type_ })) =>
+            {
+                first.name = syn::Ident::new("receiver", first.name.span());
+                guarded_receiver = matches!(
+                    first.modifier,
+                    Some(
+                        SpreadModifier::Custom(_)
+                            | SpreadModifier::CustomRef(_, _)
+                            | SpreadModifier::CustomRefMut(_, _, _)
+                    )
+                );
                 None
             }
-        } else {
-            None
+            Some(first) if first.name == "self" => {
+                if let (Some(_), Some(_)) = (&first.modifier, &first.type_) {
+                    return Err(syn::Error::new(
+                        first.name.span(),
+                        "modifiers are not allowed before a `self: Type` receiver",
+                    ));
+                }
+                fields.pop_front()
+            }
+            _ => None,
         };
 
         // Forbid other self
         for field in &fields {
-            if field.type_.is_none() {
+            if field.name == "self" {
                 return Err(syn::Error::new(
                     field.name.span(),
                     "`self` is only allowed once in first position",
@@ -227,38 +1193,662 @@ impl Parse for FnStruct {
             }
         }
 
-        // Fields should either all have values or none.
-        let have_value_count = fields.iter().filter(|field| field.value.is_some()).count();
-        if have_value_count != 0 && have_value_count != fields.len() {
+        // `@call` arguments are not stored, and are provided to `call` like `self`
+        let mut call_args = vec![];
+        let mut stored_fields = VecDeque::new();
+        for (i, field) in fields.into_iter().enumerate() {
+            let Some(token_at) = field.call_time else {
+                stored_fields.push_back(field);
+                continue;
+            };
+
+            if let Some(value) = field.value.as_ref().or(field.fallback.as_ref()) {
+                return Err(syn::Error::new(
+                    value.span(),
+                    "`@call` arguments can't have a default value",
+                ));
+            }
+            if let Some(position) = &field.position {
+                return Err(syn::Error::new(
+                    position.span(),
+                    "`@call` arguments are passed at their declared position",
+                ));
+            }
+            if let Some(attr) = field.attrs.first() {
+                return Err(syn::Error::new(
+                    attr.span(),
+                    "attributes are not allowed on `@call` arguments",
+                ));
+            }
+            if !matches!(field.vis, syn::Visibility::Inherited) {
+                return Err(syn::Error::new(
+                    field.vis.span(),
+                    "visibility is not allowed on `@call` arguments",
+                ));
+            }
+            if let Some(validation) = field.validations.first() {
+                return Err(syn::Error::new(
+                    validation.predicate.span(),
+                    "`@call` arguments can't be validated",
+                ));
+            }
+            if let Some(field) = stored_fields.iter().find(|field| field.position.is_some()) {
+                return Err(syn::Error::new(
+                    token_at.span(),
+                    format!(
+                        "`@call` arguments can't be used with `#N` positions (used by `{}`)",
+                        field.name
+                    ),
+                ));
+            }
+
+            call_args.push((i, field));
+        }
+        let mut fields = stored_fields;
+
+        // `impl Trait` types are replaced by new generic parameters of the struct, and elided
+        // lifetimes by new lifetime parameters
+        let mut impl_count = 0;
+        let mut lifetime_count = 0;
+        let mut lifetimes = vec![];
+        for field in &mut fields {
+            if let Some(type_) = &mut field.type_ {
+                walk_type(type_, &mut |type_| match type_ {
+                    syn::Type::ImplTrait(impl_trait) => {
+                        let bounds = &impl_trait.bounds;
+                        let param = syn::Ident::new(&format!("__Impl{impl_count}"), bounds.span());
+                        impl_count += 1;
+                        struct_gen.params.push(syn::parse_quote!(#param: #bounds));
+                        *type_ = syn::parse_quote!(#param);
+                    }
+                    syn::Type::Reference(reference)
+                        if reference
+                            .lifetime
+                            .as_ref()
+                            .is_none_or(|lifetime| lifetime.ident == "_") =>
+                    {
+                        let lifetime = syn::Lifetime::new(
+                            &format!("'__{lifetime_count}"),
+                            reference.and_token.span(),
+                        );
+                        lifetime_count += 1;
+                        reference.lifetime = Some(lifetime.clone());
+                        lifetimes.push(lifetime);
+                    }
+                    _ => (),
+                });
+            }
+        }
+
+        // Named lifetimes which are not declared are added to the struct
+        let declared: Vec<_> = struct_gen
+            .lifetimes()
+            .chain(call_gen.lifetimes())
+            .map(|param| param.lifetime.clone())
+            .collect();
+        for field in &fields {
+            let type_ = &field.type_;
+            for lifetime in lifetimes_in(quote! { #type_ }) {
+                if lifetime.ident != "static"
+                    && lifetime.ident != "_"
+                    && !declared.contains(&lifetime)
+                    && !lifetimes.contains(&lifetime)
+                {
+                    lifetimes.push(lifetime);
+                }
+            }
+        }
+        for lifetime in lifetimes.into_iter().rev() {
+            struct_gen.params.insert(0, syn::parse_quote!(#lifetime));
+        }
+
+        // Fields with a `#N` position are passed as the N-th argument (ignoring `self`), others
+        // fill the remaining positions in order.
+        let mut args_order: Vec<Option<usize>> = vec![None; fields.len()];
+        for (i, field) in fields.iter().enumerate() {
+            if let Some(position) = &field.position {
+                let index: usize = position.base10_parse()?;
+                match args_order.get_mut(index) {
+                    None => {
+                        return Err(syn::Error::new(
+                            position.span(),
+                            format!("position must be less than {}", fields.len()),
+                        ))
+                    }
+                    Some(Some(_)) => {
+                        return Err(syn::Error::new(
+                            position.span(),
+                            "position already used by another field",
+                        ))
+                    }
+                    Some(slot) => *slot = Some(i),
+                }
+            }
+        }
+        let mut unpositioned = fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| field.position.is_none())
+            .map(|(i, _)| i);
+        let args_order: Vec<usize> = args_order
+            .into_iter()
+            .map(|slot| {
+                slot.or_else(|| unpositioned.next())
+                    .expect("one field per position")
+            })
+            .collect();
+
+        // Only modifiers that can be evaluated in const contexts, custom functions must be const
+        if constness.is_some() {
+            for field in &fields {
+                if !matches!(
+                    field.modifier,
+                    None | Some(
+                        SpreadModifier::Ref(_)
+                            | SpreadModifier::RefMut(_, _)
+                            | SpreadModifier::Custom(_)
+                            | SpreadModifier::CustomRef(_, _)
+                            | SpreadModifier::CustomRefMut(_, _, _)
+                    )
+                ) {
+                    return Err(syn::Error::new(
+                        field.name.span(),
+                        "only `&`, `&mut` and `[path]` modifiers are allowed with a `const fn`",
+                    ));
+                }
+            }
+        }
+
+        // Fields without values default to `Default::default()` if any field has a value.
+        let have_value_count = fields
+            .iter()
+            .filter(|field| field.value.is_some() || field.fallback.is_some())
+            .count();
+
+        // Traits implemented with `#[fn_struct(impl Trait)]` have a `call` without parameters
+        let call_param = self_.as_ref().or(call_args.first().map(|(_, field)| field));
+        if let (Some(path), Some(param)) = (options.impls.first(), call_param) {
+            return Err(syn::Error::new(
+                path.span(),
+                format!(
+                    "`impl {}` requires a `call` without parameters, but `{}` is a parameter",
+                    quote! { #path },
+                    param.name
+                ),
+            ));
+        }
+        if let (Some(path), false) = (options.impls.first(), call_gen.params.is_empty()) {
+            return Err(syn::Error::new(
+                path.span(),
+                "`impl Trait` requires a `call` without generic parameters",
+            ));
+        }
+
+        // Results are only cached by the value of the struct
+        if let Some(cached) = &options.cached {
+            if call_param.is_some() || !call_gen.params.is_empty() {
+                return Err(syn::Error::new(
+                    cached.span(),
+                    "`cached` requires a `call` without parameters nor generic parameters",
+                ));
+            }
+            if let Some(token_async) = asyncness {
+                return Err(syn::Error::new(
+                    token_async.span(),
+                    "`cached` is not supported with `async` functions",
+                ));
+            }
+        }
+
+        if let Some((instrument, skip)) = &options.instrument {
+            if let Some(token_const) = constness {
+                return Err(syn::Error::new(
+                    token_const.span(),
+                    format!("`{instrument}` is not supported with `const` functions"),
+                ));
+            }
+            for name in skip {
+                if !fields.iter().any(|field| &field.name == name) {
+                    return Err(syn::Error::new(name.span(), "unknown field"));
+                }
+            }
+        }
+
+        if let (Some(spawn), Some(token_unsafe)) = (&options.spawn, unsafety) {
             return Err(syn::Error::new(
-                Span::call_site(),
-                "Fields must either all have values (`= value`) or none have",
+                token_unsafe.span(),
+                format!("`{spawn}` is not supported with `unsafe` functions"),
             ));
         }
 
+        // Modifiers can rely on coercions of the arguments, and `impl Trait` can't be named in a
+        // function pointer type
+        if let Some(check_signature) = &options.check_signature {
+            let unsupported = asyncness.is_some()
+                || fields
+                    .iter()
+                    .chain(call_args.iter().map(|(_, field)| field))
+                    .any(|field| field.modifier.is_some() || field.fallback.is_some())
+                || call_args
+                    .iter()
+                    .any(|(_, field)| contains_impl_trait(&field.type_));
+            if unsupported {
+                return Err(syn::Error::new(
+                    check_signature.span(),
+                    "`check_signature` requires a non-`async` function and fields without \
+                     modifiers, fallbacks or `impl Trait` types",
+                ));
+            }
+        }
+
+        if let Some(calls) = &options.calls_option {
+            if let Some(token_ref) = call_by_ref {
+                return Err(syn::Error::new(
+                    token_ref.span(),
+                    format!("`&` can't be used with `{calls}`, list `&self` instead"),
+                ));
+            }
+            // Fields passed by `&mut` can't be borrowed from `&self`
+            if options.calls.contains(&CallReceiver::Ref) {
+                let ref_mut_field = fields.iter().find(|field| {
+                    matches!(
+                        field.modifier,
+                        Some(SpreadModifier::RefMut(_, _) | SpreadModifier::CustomRefMut(_, _, _))
+                    )
+                });
+                if let Some(field) = ref_mut_field {
+                    return Err(syn::Error::new(
+                        field.name.span(),
+                        "fields passed by `&mut` can't be used with `call_ref(&self)`",
+                    ));
+                }
+            }
+        }
+
         Ok(FnStruct {
             struct_attr,
+            options,
             vis,
             call_by_ref,
             struct_name,
             struct_gen,
             call_gen,
+            constness,
+            asyncness,
+            unsafety,
+            abi,
             fn_path,
             fields,
+            args_order,
+            call_args,
             return_type,
+            map_err,
             self_,
+            guarded_receiver,
             impl_default: have_value_count > 0,
         })
     }
 }
 
+// Calls `visit` on `type_` then on the types it contains
+pub fn walk_type(type_: &mut syn::Type, visit: &mut impl FnMut(&mut syn::Type)) {
+    visit(type_);
+
+    match type_ {
+        syn::Type::Reference(syn::TypeReference { elem, .. })
+        | syn::Type::Paren(syn::TypeParen { elem, .. })
+        | syn::Type::Group(syn::TypeGroup { elem, .. })
+        | syn::Type::Slice(syn::TypeSlice { elem, .. })
+        | syn::Type::Array(syn::TypeArray { elem, .. })
+        | syn::Type::Ptr(syn::TypePtr { elem, .. }) => walk_type(elem, visit),
+        syn::Type::Tuple(tuple) => {
+            for elem in &mut tuple.elems {
+                walk_type(elem, visit);
+            }
+        }
+        syn::Type::Path(syn::TypePath { path, .. }) => {
+            for segment in &mut path.segments {
+                if let syn::PathArguments::AngleBracketed(args) = &mut segment.arguments {
+                    for arg in &mut args.args {
+                        if let syn::GenericArgument::Type(type_) = arg {
+                            walk_type(type_, visit);
+                        }
+                    }
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+// Lifetimes appearing in the tokens, in order of appearance
+fn lifetimes_in(tokens: TokenStream) -> Vec<syn::Lifetime> {
+    let mut lifetimes = vec![];
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            proc_macro2::TokenTree::Punct(punct) if punct.as_char() == '\'' => {
+                if let Some(proc_macro2::TokenTree::Ident(ident)) = tokens.peek() {
+                    let lifetime = syn::Lifetime {
+                        apostrophe: punct.span(),
+                        ident: ident.clone(),
+                    };
+                    if !lifetimes.contains(&lifetime) {
+                        lifetimes.push(lifetime);
+                    }
+                }
+            }
+            proc_macro2::TokenTree::Group(group) => {
+                for lifetime in lifetimes_in(group.stream()) {
+                    if !lifetimes.contains(&lifetime) {
+                        lifetimes.push(lifetime);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    lifetimes
+}
+
+// Inserts the `@call` arguments at their position among the arguments from fields
+fn interleave<T>(
+    stored: impl IntoIterator<Item = T>,
+    call_time: impl IntoIterator<Item = (usize, T)>,
+) -> Vec<T> {
+    let mut all: Vec<T> = stored.into_iter().collect();
+    for (i, arg) in call_time {
+        all.insert(i, arg);
+    }
+    all
+}
+
+fn contains_impl_trait(type_: &Option<syn::Type>) -> bool {
+    let mut found = false;
+    if let Some(type_) = type_ {
+        walk_type(&mut type_.clone(), &mut |type_| {
+            found |= matches!(type_, syn::Type::ImplTrait(_));
+        });
+    }
+    found
+}
+
+// Formats tokens for documentation, without the spaces of `TokenStream::to_string`
+fn doc_string(tokens: TokenStream) -> String {
+    let mut doc = tokens.to_string();
+    for (from, to) in [
+        (" :: ", "::"),
+        (":: ", "::"),
+        (" < ", "<"),
+        ("< ", "<"),
+        (" <", "<"),
+        (" >", ">"),
+        (" ,", ","),
+        (" . ", "."),
+        (" (", "("),
+        ("( ", "("),
+        (" )", ")"),
+        ("& ", "&"),
+    ] {
+        doc = doc.replace(from, to);
+    }
+    doc
+}
+
+fn contains_self(tokens: TokenStream) -> bool {
+    tokens.into_iter().any(|token| match token {
+        proc_macro2::TokenTree::Ident(ident) => ident == "Self",
+        proc_macro2::TokenTree::Group(group) => contains_self(group.stream()),
+        _ => false,
+    })
+}
+
+pub fn replace_self(tokens: TokenStream, self_type: &TokenStream) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            proc_macro2::TokenTree::Ident(ident) if ident == "Self" => quote! { #self_type },
+            proc_macro2::TokenTree::Group(group) => {
+                let mut replaced = proc_macro2::Group::new(
+                    group.delimiter(),
+                    replace_self(group.stream(), self_type),
+                );
+                replaced.set_span(group.span());
+                quote! { #replaced }
+            }
+            token => quote! { #token },
+        })
+        .collect()
+}
+
+// Extracts `T` and `E` from a `Result<T, E>` return type
+fn result_types(return_type: &syn::Type) -> Option<(syn::Type, syn::Type)> {
+    let syn::Type::Path(syn::TypePath { qself: None, path }) = return_type else {
+        return None;
+    };
+    let last = path.segments.last().filter(|last| last.ident == "Result")?;
+    let syn::PathArguments::AngleBracketed(args) = &last.arguments else {
+        return None;
+    };
+    match args.args.iter().collect::<Vec<_>>()[..] {
+        [syn::GenericArgument::Type(ok), syn::GenericArgument::Type(err)] => {
+            Some((ok.clone(), err.clone()))
+        }
+        _ => None,
+    }
+}
+
+// Options provided with `#[fn_struct(..)]`
+#[derive(Default)]
+struct FnStructOptions {
+    // `impl Trait`, implements a user trait with an `Output` type and a `call` method
+    impls: Vec<syn::Path>,
+    // `arbitrary`, implements `proptest::arbitrary::Arbitrary`
+    arbitrary: Option<syn::Ident>,
+    // `cached`, generates `call_cached` and `call_cached_in` memoizing results
+    cached: Option<syn::Ident>,
+    // `spawn`, generates `spawn` (async functions) or `spawn_blocking` using `tokio`
+    spawn: Option<syn::Ident>,
+    // `instrument` or `instrument(skip(fields))`, wraps the call in a `tracing` span
+    instrument: Option<(syn::Ident, Vec<syn::Ident>)>,
+    // `call_attr(attrs)`, attributes of the `call` methods like `track_caller` or `inline`
+    call_attrs: Vec<syn::Meta>,
+    // `serde`, derives `Serialize` and `Deserialize`
+    serde: Option<syn::Ident>,
+    // `calls(self, &self, &mut self)`, generates `call`, `call_ref` and `call_mut`
+    calls_option: Option<syn::Ident>,
+    calls: Vec<CallReceiver>,
+    // `new`, generates `new` taking the fields without default value, implied by
+    // `#[non_exhaustive]`
+    new: Option<syn::Ident>,
+    // `patch`, generates a `<Name>Patch` struct with optional fields and `call_with`
+    patch: Option<syn::Ident>,
+    // `check_signature`, checks the signature of the function against the types of the fields
+    check_signature: Option<syn::Ident>,
+    // `tuple`, converts the struct from and into a tuple of its fields
+    tuple: Option<syn::Ident>,
+    // `display_call`, generates `display_call` rendering the call with `Debug`
+    display_call: Option<syn::Ident>,
+    // `setters`, generates chainable `with_<field>` setters
+    setters: Option<syn::Ident>,
+    // `metadata`, generates `ARG_NAMES`, `ARG_COUNT` and `FN_PATH` constants
+    metadata: Option<syn::Ident>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CallReceiver {
+    // `call(self)`
+    Value,
+    // `call_ref(&self)`
+    Ref,
+    // `call_mut(&mut self)`
+    Mut,
+}
+
+impl FnStructOptions {
+    fn parse_list(&mut self, input: ParseStream) -> syn::Result<()> {
+        while !input.is_empty() {
+            let lookahead = input.lookahead1();
+            if lookahead.peek(Token![impl]) {
+                let _: Token![impl] = input.parse()?;
+                self.impls.push(input.parse()?);
+            } else if lookahead.peek(syn::Ident) {
+                let option: syn::Ident = input.parse()?;
+                match option.to_string().as_str() {
+                    "arbitrary" => {
+                        if !cfg!(feature = "proptest") {
+                            return Err(syn::Error::new(
+                                option.span(),
+                                "`arbitrary` requires the `proptest` feature of `spread_macros`",
+                            ));
+                        }
+                        self.arbitrary = Some(option);
+                    }
+                    "cached" => self.cached = Some(option),
+                    "serde" => self.serde = Some(option),
+                    "new" => self.new = Some(option),
+                    "patch" => self.patch = Some(option),
+                    "check_signature" => self.check_signature = Some(option),
+                    "tuple" => self.tuple = Some(option),
+                    "display_call" => self.display_call = Some(option),
+                    "setters" => self.setters = Some(option),
+                    "metadata" => self.metadata = Some(option),
+                    "call_attr" => {
+                        let content;
+                        parenthesized!(content in input);
+                        self.call_attrs.extend(
+                            Punctuated::<syn::Meta, Token![,]>::parse_terminated(&content)?,
+                        );
+                    }
+                    "spawn" => {
+                        if !cfg!(feature = "tokio") {
+                            return Err(syn::Error::new(
+                                option.span(),
+                                "`spawn` requires the `tokio` feature of `spread_macros`",
+                            ));
+                        }
+                        self.spawn = Some(option);
+                    }
+                    "instrument" => {
+                        if !cfg!(feature = "tracing") {
+                            return Err(syn::Error::new(
+                                option.span(),
+                                "`instrument` requires the `tracing` feature of `spread_macros`",
+                            ));
+                        }
+
+                        let mut skip = vec![];
+                        if input.peek(syn::token::Paren) {
+                            let content;
+                            parenthesized!(content in input);
+                            let skip_ident: syn::Ident = content.parse()?;
+                            if skip_ident != "skip" {
+                                return Err(syn::Error::new(skip_ident.span(), "expected `skip`"));
+                            }
+                            let names;
+                            parenthesized!(names in content);
+                            skip.extend(Punctuated::<syn::Ident, Token![,]>::parse_terminated(
+                                &names,
+                            )?);
+                        }
+                        self.instrument = Some((option, skip));
+                    }
+                    "calls" => {
+                        let content;
+                        parenthesized!(content in input);
+                        let receivers =
+                            Punctuated::<syn::Receiver, Token![,]>::parse_terminated(&content)?;
+                        for receiver in receivers {
+                            if receiver.colon_token.is_some() {
+                                return Err(syn::Error::new(
+                                    receiver.span(),
+                                    "expected `self`, `&self` or `&mut self`",
+                                ));
+                            }
+                            let call = match &receiver.reference {
+                                None => CallReceiver::Value,
+                                Some((_, Some(lifetime))) => {
+                                    return Err(syn::Error::new(
+                                        lifetime.span(),
+                                        "lifetimes are not allowed in `calls`",
+                                    ))
+                                }
+                                Some((_, None)) if receiver.mutability.is_some() => {
+                                    CallReceiver::Mut
+                                }
+                                Some((_, None)) => CallReceiver::Ref,
+                            };
+                            if self.calls.contains(&call) {
+                                return Err(syn::Error::new(
+                                    receiver.span(),
+                                    "call variant listed multiple times",
+                                ));
+                            }
+                            self.calls.push(call);
+                        }
+                        if self.calls.is_empty() {
+                            return Err(syn::Error::new(
+                                option.span(),
+                                "`calls` must list at least one of `self`, `&self` or `&mut self`",
+                            ));
+                        }
+                        self.calls_option = Some(option);
+                    }
+                    _ => return Err(syn::Error::new(option.span(), "unknown option")),
+                }
+            } else {
+                return Err(lookahead.error());
+            }
+
+            if input.is_empty() {
+                break;
+            }
+            let _: Token![,] = input.parse()?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Clone)]
 pub struct TypedField {
+    // Outer attributes forwarded to the struct field
+    pub attrs: Vec<syn::Attribute>,
+    // `#[validate(predicate, "message")]` attributes, checked by `validate`
+    pub validations: Vec<Validation>,
+    // Visibility of the struct field
+    pub vis: syn::Visibility,
+    // `#N`, position of the argument in the function
+    pub position: Option<syn::LitInt>,
+    // `@call`, the argument is a parameter of `call` instead of a field
+    pub call_time: Option<Token![@]>,
     pub modifier: Option<SpreadModifier>,
     pub name: syn::Ident,
-    // None = Self
+    // None = Self, Some on `self` = stored receiver
     pub type_: Option<syn::Type>,
     pub value: Option<syn::Expr>,
+    // `field: Option<T> ?= fallback`, the function receives `fallback` if the field is `None`
+    pub fallback: Option<syn::Expr>,
+}
+
+#[derive(Clone)]
+pub struct Validation {
+    pub predicate: syn::Expr,
+    pub message: Option<syn::LitStr>,
+}
+
+impl Parse for Validation {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let predicate = input.parse()?;
+        let message = if input.peek(Token![,]) {
+            let _: Token![,] = input.parse()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        Ok(Self { predicate, message })
+    }
 }
 
 impl From<TypedField> for Field {
@@ -273,6 +1863,12 @@ impl From<TypedField> for Field {
             modifier,
             name,
             is_mut: None,
+            member: None,
+            index: None,
+            element: None,
+            call: None,
+            ty: None,
+            optional: None,
             value,
         }
     }
@@ -280,10 +1876,81 @@ impl From<TypedField> for Field {
 
 impl Parse for TypedField {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        // `Attribute::parse_outer` would fail on the `#N` position
+        let mut attrs = vec![];
+        let mut validations: Vec<Validation> = vec![];
+        while input.peek(Token![#]) && input.peek2(syn::token::Bracket) {
+            let content;
+            let attr = syn::Attribute {
+                pound_token: input.parse()?,
+                style: syn::AttrStyle::Outer,
+                bracket_token: bracketed!(content in input),
+                meta: content.parse()?,
+            };
+
+            if attr.path().is_ident("validate") {
+                validations.push(attr.parse_args()?);
+            } else {
+                attrs.push(attr);
+            }
+        }
+
+        let vis: syn::Visibility = input.parse()?;
+
+        let position: Option<syn::LitInt> = if input.peek(Token![#]) {
+            let _: Token![#] = input.parse()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+
+        let call_time: Option<Token![@]> = if input.peek(Token![@]) {
+            let token_at = input.parse()?;
+            let call: syn::Ident = input.parse()?;
+            if call != "call" {
+                return Err(syn::Error::new(call.span(), "expected `@call`"));
+            }
+            Some(token_at)
+        } else {
+            None
+        };
+
         let modifier = SpreadModifier::parse(input)?;
         let name = input.call(syn::Ident::parse_any)?;
 
-        if &name.to_string() == "self" {
+        if &name.to_string() == "self" && !input.peek(Token![:]) {
+            if let Some(position) = &position {
+                return Err(syn::Error::new(
+                    position.span(),
+                    "`self` is always the first argument",
+                ));
+            }
+            if let Some(token_at) = call_time {
+                return Err(syn::Error::new(
+                    token_at.span(),
+                    "`self` is already a parameter of `call` when it has no type",
+                ));
+            }
+            if !matches!(vis, syn::Visibility::Inherited) {
+                return Err(syn::Error::new(
+                    vis.span(),
+                    "visibility is not allowed on `self` when it is not stored in the struct",
+                ));
+            }
+
+            if let Some(attr) = attrs.first() {
+                return Err(syn::Error::new(
+                    attr.span(),
+                    "attributes are not allowed on `self` when it is not stored in the struct",
+                ));
+            }
+            if let Some(validation) = validations.first() {
+                return Err(syn::Error::new(
+                    validation.predicate.span(),
+                    "`self` can't be validated when it is not stored in the struct",
+                ));
+            }
+
             if matches!(
                 &modifier,
                 Some(
@@ -299,16 +1966,30 @@ impl Parse for TypedField {
             }
 
             Ok(Self {
+                attrs,
+                validations,
+                vis,
+                position,
+                call_time,
                 modifier,
                 name,
                 type_: None,
                 value: None,
+                fallback: None,
             })
         } else {
             let _: Token![:] = input.parse()?;
             let type_ = Some(input.parse()?);
 
-            let value = {
+            let fallback = if input.peek(Token![?]) && input.peek2(Token![=]) {
+                let _: Token![?] = input.parse()?;
+                let _: Token![=] = input.parse()?;
+                Some(input.parse()?)
+            } else {
+                None
+            };
+
+            let value = if fallback.is_none() {
                 let lookahead = input.lookahead1();
 
                 if lookahead.peek(Token![=]) {
@@ -318,13 +1999,21 @@ impl Parse for TypedField {
                 } else {
                     None
                 }
+            } else {
+                None
             };
 
             Ok(Self {
+                attrs,
+                validations,
+                vis,
+                position,
+                call_time,
                 modifier,
                 name,
                 type_,
                 value,
+                fallback,
             })
         }
     }
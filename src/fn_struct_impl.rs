@@ -0,0 +1,243 @@
+use {
+    super::{fn_struct::*, *},
+    syn::ext::IdentExt,
+};
+
+pub fn fn_struct_impl(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let attr = TokenStream::from(attr);
+    if let Some(token) = attr.into_iter().next() {
+        return syn::Error::new(token.span(), "`fn_struct_impl` doesn't take arguments")
+            .into_compile_error()
+            .into();
+    }
+
+    let mut item_impl = parse_macro_input!(item as syn::ItemImpl);
+
+    match expand(&mut item_impl) {
+        Ok(structs) => quote! {
+            #item_impl
+            #( #structs )*
+        },
+        Err(err) => {
+            let err = err.into_compile_error();
+            quote! {
+                #item_impl
+                #err
+            }
+        }
+    }
+    .into()
+}
+
+fn expand(item_impl: &mut syn::ItemImpl) -> syn::Result<Vec<TokenStream>> {
+    if let Some((_, trait_, _)) = &item_impl.trait_ {
+        return Err(syn::Error::new(
+            trait_.span(),
+            "`fn_struct_impl` only supports inherent impls",
+        ));
+    }
+
+    let self_ty = &item_impl.self_ty;
+    let self_ty_tokens = quote! { #self_ty };
+    let type_name = match &**self_ty {
+        syn::Type::Path(syn::TypePath { path, .. }) => match path.segments.last() {
+            Some(segment) => segment.ident.unraw().to_string(),
+            None => return Err(syn::Error::new(self_ty.span(), "expected a type path")),
+        },
+        _ => return Err(syn::Error::new(self_ty.span(), "expected a type path")),
+    };
+
+    let mut structs = vec![];
+    for item in &mut item_impl.items {
+        let syn::ImplItem::Fn(method) = item else {
+            continue;
+        };
+
+        // `#[fn_struct_impl(skip)]` opts out a method, and is removed as it is not a real attribute
+        let mut skip = false;
+        let mut skip_err = None;
+        method.attrs.retain(|attr| {
+            if !attr.path().is_ident("fn_struct_impl") {
+                return true;
+            }
+            match attr.parse_args::<syn::Ident>() {
+                Ok(ident) if ident == "skip" => skip = true,
+                _ => skip_err = Some(syn::Error::new(attr.span(), "expected `skip`")),
+            }
+            false
+        });
+        if let Some(err) = skip_err {
+            return Err(err);
+        }
+
+        if skip || !matches!(method.vis, syn::Visibility::Public(_)) {
+            continue;
+        }
+
+        structs.push(method_struct(
+            &item_impl.generics,
+            &self_ty_tokens,
+            &type_name,
+            method,
+        )?);
+    }
+
+    Ok(structs)
+}
+
+fn method_struct(
+    impl_gen: &syn::Generics,
+    self_ty: &TokenStream,
+    type_name: &str,
+    method: &syn::ImplItemFn,
+) -> syn::Result<TokenStream> {
+    let syn::Signature {
+        constness,
+        asyncness,
+        unsafety,
+        ident,
+        generics,
+        inputs,
+        output,
+        ..
+    } = &method.sig;
+    let vis = &method.vis;
+
+    let struct_name = syn::Ident::new(
+        &format!("{type_name}{}Args", camel_case(&ident.unraw().to_string())),
+        ident.span(),
+    );
+
+    // `impl Trait` arguments are replaced by generic parameters of the struct, with their bounds
+    // on `call` as they can refer to other generic parameters
+    let mut impl_params = vec![];
+    let mut call_predicates: Vec<syn::WherePredicate> = vec![];
+
+    let mut args = vec![];
+    let mut args_type = vec![];
+    for (i, input) in inputs.iter().enumerate() {
+        match input {
+            syn::FnArg::Receiver(receiver) => {
+                let syn::Receiver {
+                    reference,
+                    mutability,
+                    colon_token,
+                    ty,
+                    ..
+                } = receiver;
+                args.push(match (reference, colon_token) {
+                    (_, Some(_)) => quote! { self: #ty },
+                    (Some((and, _)), None) => quote! { #and #mutability self },
+                    (None, None) => quote! { self },
+                });
+            }
+            syn::FnArg::Typed(syn::PatType { pat, ty, .. }) => {
+                let name = match &**pat {
+                    syn::Pat::Ident(syn::PatIdent { ident, .. }) => ident.clone(),
+                    _ => syn::Ident::new(&format!("arg{i}"), pat.span()),
+                };
+                let mut ty: syn::Type = syn::parse2(replace_self(quote! { #ty }, self_ty))?;
+                walk_type(&mut ty, &mut |ty| {
+                    if let syn::Type::ImplTrait(impl_trait) = ty {
+                        let bounds = &impl_trait.bounds;
+                        let param = syn::Ident::new(
+                            &format!("__Impl{}", impl_params.len()),
+                            impl_trait.span(),
+                        );
+                        call_predicates.push(syn::parse_quote!(#param: #bounds));
+                        *ty = syn::parse_quote!(#param);
+                        impl_params.push(param);
+                    }
+                });
+                args.push(quote! { #vis #name: #ty });
+                args_type.push(ty);
+            }
+        }
+    }
+
+    let output = replace_self(quote! { #output }, self_ty);
+
+    // Generic parameters used by the arguments belong to the struct, others to `call`. Bounds are
+    // all checked by `call`, as they can refer to generic parameters of `call`.
+    let args_type = quote! { #( #args_type )* };
+    let mut struct_gen = syn::Generics::default();
+    let mut call_gen = syn::Generics::default();
+    for param in impl_gen.params.iter().chain(&generics.params) {
+        let (used, bounds) = match param {
+            syn::GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                let bounds = &param.bounds;
+                (
+                    contains_ident(&args_type, &lifetime.ident),
+                    (!bounds.is_empty()).then(|| syn::parse_quote!(#lifetime: #bounds)),
+                )
+            }
+            syn::GenericParam::Type(param) => {
+                let ident = &param.ident;
+                let bounds = &param.bounds;
+                (
+                    contains_ident(&args_type, ident),
+                    (!bounds.is_empty()).then(|| syn::parse_quote!(#ident: #bounds)),
+                )
+            }
+            syn::GenericParam::Const(param) => (contains_ident(&args_type, &param.ident), None),
+        };
+
+        if !used {
+            call_gen.params.push(param.clone());
+            continue;
+        }
+
+        let mut param = param.clone();
+        match &mut param {
+            syn::GenericParam::Lifetime(param) => param.bounds.clear(),
+            syn::GenericParam::Type(param) => param.bounds.clear(),
+            syn::GenericParam::Const(_) => (),
+        }
+        struct_gen.params.push(param);
+        call_predicates.extend(bounds);
+    }
+    for param in impl_params {
+        struct_gen.params.push(syn::parse_quote!(#param));
+    }
+    call_predicates.extend(
+        impl_gen
+            .where_clause
+            .iter()
+            .chain(&generics.where_clause)
+            .flat_map(|where_clause| where_clause.predicates.iter().cloned()),
+    );
+    let call_where =
+        (!call_predicates.is_empty()).then(|| quote! { where #( #call_predicates ),* });
+
+    let tokens = quote! {
+        #vis struct #struct_name #struct_gen
+        for #call_gen #call_where #constness #asyncness #unsafety fn <#self_ty>::#ident ( #( #args ),* ) #output
+    };
+
+    Ok(syn::parse2::<FnStruct>(tokens)?.expand())
+}
+
+fn camel_case(snake: &str) -> String {
+    snake
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn contains_ident(tokens: &TokenStream, ident: &syn::Ident) -> bool {
+    tokens.clone().into_iter().any(|token| match token {
+        proc_macro2::TokenTree::Ident(token) => &token == ident,
+        proc_macro2::TokenTree::Group(group) => contains_ident(&group.stream(), ident),
+        _ => false,
+    })
+}
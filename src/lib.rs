@@ -1,11 +1,21 @@
 #![doc = include_str!("../README.md")]
 
 mod anon;
+mod anon_fields;
 mod assert_fields_eq;
+mod assert_fields_matches;
+mod assert_fields_ne;
 mod common;
+mod compare_fields;
+mod deanon;
+mod fields_eq;
 mod fn_struct;
+mod fn_struct_impl;
+mod sasync;
+mod sclosure;
 mod slet;
 mod spread;
+mod try_assert_fields_eq;
 
 use {
     proc_macro2::{Span, TokenStream},
@@ -34,9 +44,30 @@ use {
 /// Fields can be listed as follows:
 /// - `field,`: field which captures a variable of the same name
 /// - `field: value,`: field with provided value
+/// - `field ?: value,`: field with provided `Option` value, which is taken from `..remaining` if
+///   it is `None`
 /// - `{ field1, field2 } in source,`: fields extracted from another struct
+/// - `{ method1(), method2() } in source,`: fields extracted from another struct by calling getter
+///   methods without arguments, which allows to use sources with private fields
+/// - `{ 0 as field1, 1 as field2 } in source,`: fields extracted from a tuple or tuple struct by
+///   index
+/// - `{ [0] field1, [1] field2 } in source,`: fields extracted from an array, slice or any type
+///   implementing `Index<usize>` by index
+/// - `{ field1 as name1, field2 } in source,`: fields extracted from another struct, with
+///   `field1` renamed to `name1`
+/// - `{ field1, field2 } in source as prefix_*,`: fields extracted from another struct, and
+///   renamed with a prefix (or a suffix with `as *_suffix`)
+/// - `{ field1, field2 } in source else fallback,`: fields extracted from an `Option` or `Result`
+///   source, using the (eagerly evaluated) `fallback` if it is `None` or `Err`
+/// - `map { field1, field2 } in source,`: fields looked up by name in a map with string keys such
+///   as `HashMap` or `BTreeMap`, panicking if a key is missing
 /// - `..remaining`: same as in [struct update syntax], can only appear last without a trailing
 ///   comma
+/// - `..>remaining`: same as `..remaining`, but converts `remaining` with `Into` first, which
+///   allows to use a lighter struct only containing defaults
+///
+/// Field names can be raw identifiers such as `r#type`, which are supported by all the macros of
+/// this crate.
 ///
 /// Each field name can be prefixed by a modifier allowing to perform common transformations that
 /// usually requires repeating the field name. They are placed before the field and mean the
@@ -46,10 +77,30 @@ use {
 /// - `+field`: clones the value, can be used with `&source` to not consume the source
 /// - `>field`: converts the value with `Into`
 /// - `+>field`: clones then converts the value with `Into`, can be used with `&source` to not
-/// consume the source
+///   consume the source
+/// - `~field`: wraps the value in `Cow::Owned`
+/// - `&~field`: wraps the reference in `Cow::Borrowed`, convert a `T` field to `Cow<'_, T>`
+/// - `+~field`: clones the value then wraps it in `Cow::Owned`, can be used with `&source` to not
+///   consume the source
+/// - `?field`: unwraps an `Option` (or `Result`) value with `unwrap_or_default`, convert an
+///   `Option<T>` field to `T`
+/// - `+?field`: clones then unwraps the value with `unwrap_or_default`, can be used with `&source`
+///   to not consume the source
+/// - `+&field`: clones an `Arc` or `Rc` handle with `Arc::clone`/`Rc::clone`, which fails to
+///   compile for other types to avoid accidental deep clones
+/// - `^field`: upgrades a `Weak` (from `Arc` or `Rc`), panicking if the value was dropped
+/// - `^?field`: upgrades a `Weak`, returning `Default::default()` from the enclosing function or
+///   closure if the value was dropped (which is `()` or `None` for most functions)
 /// - `[path] field`: pass the value to function at `path`. This path can contain module separators and turbofish.
 ///   You can use this to perform custom transformations, or use more explicit alternatives to `+` and `>`. `field`
 ///   can be prefixed with `&` or `&mut` to pass a reference to the function instead of moving/copying it.
+/// - `[assert(predicate)] field`: checks that the value satisfies `predicate`, which is called
+///   with a reference to the value, and panics with the name of the field otherwise.
+///   `[debug_assert(predicate)]` only performs the check when debug assertions are enabled.
+/// - `field @ Type`: not a prefix but a suffix that can be combined with other modifiers, ascribes
+///   the type of the value after other modifiers are applied. It allows `>` to know its target
+///   type when it can't be inferred otherwise, such as in [`anon!`](crate::anon!) or with generic
+///   targets.
 ///
 /// Here is an exemple showing all the modifers:
 ///
@@ -135,6 +186,471 @@ use {
 ///     [to_lowercase] custom,
 ///     ..first
 /// });
+/// ```
+///
+/// Spread lists can call getter methods instead of reading fields, with modifiers applying to
+/// the returned value:
+///
+/// ```rust
+/// use spread_macros::spread;
+///
+/// struct Conn {
+///     name: String,
+///     id: u32,
+/// }
+///
+/// impl Conn {
+///     fn name(&self) -> &str {
+///         &self.name
+///     }
+///
+///     fn id(&self) -> u32 {
+///         self.id
+///     }
+/// }
+///
+/// struct Info {
+///     name: String,
+///     id: u64,
+/// }
+///
+/// let conn = Conn { name: "conn".to_string(), id: 42 };
+/// let info = spread!(Info {
+///     { >name(), >id() } in &conn,
+/// });
+///
+/// assert_eq!(info.name, "conn");
+/// assert_eq!(info.id, 42);
+/// ```
+///
+/// Tuples and tuple structs can be spread by index into named fields:
+///
+/// ```rust
+/// use spread_macros::spread;
+///
+/// struct Point {
+///     x: u64,
+///     y: u64,
+/// }
+///
+/// let tuple = (1u32, 2u64);
+/// let point = spread!(Point {
+///     { >0 as x, 1 as y } in tuple,
+/// });
+///
+/// assert_eq!(point.x, 1);
+/// assert_eq!(point.y, 2);
+/// ```
+///
+/// Structs with `Cow` fields can be filled from owned or borrowed values:
+///
+/// ```rust
+/// use {spread_macros::spread, std::borrow::Cow};
+///
+/// struct Source {
+///     name: String,
+///     title: String,
+/// }
+///
+/// struct Page<'a> {
+///     name: Cow<'a, str>,
+///     title: Cow<'a, str>,
+///     body: Cow<'a, str>,
+/// }
+///
+/// let source = Source {
+///     name: "page".to_string(),
+///     title: "Title".to_string(),
+/// };
+/// let body = "body".to_string();
+///
+/// let page = spread!(Page {
+///     ~body,
+///     { &~name, +~title } in &source,
+/// });
+///
+/// assert!(matches!(page.name, Cow::Borrowed("page")));
+/// assert!(matches!(page.title, Cow::Owned(_)));
+/// ```
+///
+/// Optional fields can be flattened into concrete fields, using their default when `None`:
+///
+/// ```rust
+/// use spread_macros::spread;
+///
+/// struct PartialConfig {
+///     name: Option<String>,
+///     retries: Option<u32>,
+/// }
+///
+/// struct Config {
+///     name: String,
+///     retries: u32,
+/// }
+///
+/// let partial = PartialConfig {
+///     name: Some("config".to_string()),
+///     retries: None,
+/// };
+///
+/// let config = spread!(Config {
+///     { +?name, ?retries } in &partial,
+/// });
+///
+/// assert_eq!(config.name, "config");
+/// assert_eq!(config.retries, 0);
+/// ```
+///
+/// Values can be validated while constructing the struct:
+///
+/// ```rust,should_panic
+/// use spread_macros::spread;
+///
+/// struct Server {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// let host = "localhost".to_string();
+/// let port = 0;
+///
+/// // panics with "invalid value for field `port`: | v | * v > 0"
+/// let server = spread!(Server {
+///     [assert(|v| !v.is_empty())] host,
+///     [assert(|v| *v > 0)] port,
+/// });
+/// ```
+///
+/// ```rust
+/// # use spread_macros::spread;
+/// # struct Server {
+/// #     host: String,
+/// #     port: u16,
+/// # }
+/// let (host, port) = ("localhost".to_string(), 8080);
+///
+/// let server = spread!(Server {
+///     [assert(|v| { !v.is_empty() })] host,
+///     [debug_assert(|v| { *v > 1024 })] port,
+/// });
+/// assert_eq!(server.port, 8080);
+/// ```
+///
+/// Spread lists can add a prefix or a suffix to the name of all their fields with `as prefix_*` or
+/// `as *_suffix`, the fields being listed with their names in the source:
+///
+/// ```rust
+/// use spread_macros::spread;
+///
+/// struct Legacy {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// struct Config {
+///     cfg_host: String,
+///     cfg_port: u16,
+///     host_fallback: String,
+/// }
+///
+/// let legacy = Legacy {
+///     host: "localhost".to_string(),
+///     port: 8080,
+/// };
+///
+/// let config = spread!(Config {
+///     { +host } in &legacy as *_fallback,
+///     { +host, port } in &legacy as cfg_*,
+/// });
+///
+/// assert_eq!(config.cfg_host, "localhost");
+/// assert_eq!(config.cfg_port, 8080);
+/// assert_eq!(config.host_fallback, "localhost");
+/// ```
+///
+/// Map spread lists look up each field by its name. Values taken from an owned map are removed
+/// from it, while values of a borrowed `&map` must be `Copy`, cloned with `+` or borrowed with `&`:
+///
+/// ```rust
+/// use {spread_macros::spread, std::collections::HashMap};
+///
+/// fn parse(value: &str) -> u16 {
+///     value.parse().unwrap()
+/// }
+///
+/// struct Config {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// let map: HashMap<String, String> = [
+///     ("host".to_string(), "localhost".to_string()),
+///     ("port".to_string(), "8080".to_string()),
+/// ]
+/// .into();
+///
+/// let config = spread!(Config {
+///     map {
+///         +host,
+///         [parse] &port,
+///     } in &map,
+/// });
+///
+/// assert_eq!(config.host, "localhost");
+/// assert_eq!(config.port, 8080);
+///
+/// let config = spread!(Config {
+///     map { host } in map,
+///     port: 8080,
+/// });
+///
+/// assert_eq!(config.host, "localhost");
+/// ```
+///
+/// Types that only expose a builder can be used by writing `builder` followed by an expression
+/// creating the builder. Each field is then passed to the setter method of the same name, and the
+/// braces can be followed by method calls to finish building. `..remaining` is not allowed in this
+/// mode.
+///
+/// ```rust
+/// use spread_macros::spread;
+///
+/// #[derive(Default)]
+/// struct ClientBuilder {
+///     timeout: u64,
+///     agent: String,
+///     tls: bool,
+/// }
+///
+/// impl ClientBuilder {
+///     fn timeout(self, timeout: u64) -> Self {
+///         Self { timeout, ..self }
+///     }
+///
+///     fn agent(self, agent: String) -> Self {
+///         Self { agent, ..self }
+///     }
+///
+///     fn tls(self, tls: bool) -> Self {
+///         Self { tls, ..self }
+///     }
+///
+///     fn build(self) -> Result<(u64, String, bool), ()> {
+///         Ok((self.timeout, self.agent, self.tls))
+///     }
+/// }
+///
+/// #[derive(Default)]
+/// struct Defaults {
+///     tls: bool,
+/// }
+///
+/// fn client() -> Result<(u64, String, bool), ()> {
+///     let agent = "agent";
+///     let defaults = Defaults { tls: true };
+///
+///     let client = spread!(builder ClientBuilder::default() {
+///         timeout: 10,
+///         +>agent,
+///         { tls } in defaults,
+///     }.build()?);
+///
+///     Ok(client)
+/// }
+///
+/// assert_eq!(client(), Ok((10, "agent".to_string(), true)));
+/// ```
+///
+/// Types with private fields that can only be created with a constructor can be used by writing
+/// `fn` followed by the path of the constructor. The fields are then passed as arguments in the
+/// order they are listed, and their names only serve to capture variables and to document the
+/// call. `..remaining` is not allowed in this mode.
+///
+/// ```rust
+/// use spread_macros::spread;
+///
+/// mod private {
+///     pub struct Foo {
+///         a: u32,
+///         b: u64,
+///         c: String,
+///     }
+///
+///     impl Foo {
+///         pub fn new(a: u32, b: u64, c: String) -> Self {
+///             Self { a, b, c }
+///         }
+///
+///         pub fn sum(&self) -> u64 {
+///             self.a as u64 + self.b + self.c.len() as u64
+///         }
+///     }
+/// }
+///
+/// let a = 1u32;
+/// let b = 2u32;
+/// let c = "three".to_string();
+///
+/// let foo = spread!(fn private::Foo::new { a, >b, +c });
+/// assert_eq!(foo.sum(), 1 + 2 + 5);
+/// ```
+///
+/// Partial overrides can be applied over defaults with `field ?: value`, where `value` is an
+/// `Option`. Modifiers are applied to the value inside `Some`. As in struct literals, the values
+/// of the fields are evaluated before `..remaining`.
+///
+/// ```rust
+/// use spread_macros::spread;
+///
+/// #[derive(Default)]
+/// struct Config {
+///     host: String,
+///     port: u16,
+///     retries: u32,
+/// }
+///
+/// struct Overrides {
+///     host: Option<&'static str>,
+///     port: Option<u16>,
+/// }
+///
+/// let overrides = Overrides {
+///     host: Some("localhost"),
+///     port: None,
+/// };
+///
+/// let config = spread!(Config {
+///     >host ?: overrides.host,
+///     port ?: overrides.port,
+///     ..Config {
+///         port: 8080,
+///         ..Default::default()
+///     }
+/// });
+///
+/// assert_eq!(config.host, "localhost");
+/// assert_eq!(config.port, 8080);
+/// ```
+///
+/// A struct that can be converted into the target struct can be used as the final spread with
+/// `..>remaining`:
+///
+/// ```rust
+/// use spread_macros::spread;
+///
+/// struct Config {
+///     name: String,
+///     port: u16,
+///     retries: u32,
+/// }
+///
+/// #[derive(Default)]
+/// struct ConfigDefaults;
+///
+/// impl From<ConfigDefaults> for Config {
+///     fn from(_: ConfigDefaults) -> Self {
+///         Config {
+///             name: "default".to_string(),
+///             port: 8080,
+///             retries: 3,
+///         }
+///     }
+/// }
+///
+/// let port = 80;
+/// let config = spread!(Config {
+///     port,
+///     retries ?: Some(5),
+///     ..>ConfigDefaults
+/// });
+///
+/// assert_eq!(config.name, "default");
+/// assert_eq!(config.port, 80);
+/// assert_eq!(config.retries, 5);
+/// ```
+///
+/// When no spread lists are used (and no `field ?: value` refers to `..remaining`), the macro
+/// expands to a plain struct literal. It can then be used in `const` and `static` initializers,
+/// as long as the modifiers used are themselves allowed in const contexts (`&` or `[path]` with
+/// a `const fn`).
+///
+/// ```rust
+/// use spread_macros::spread;
+///
+/// struct Limits {
+///     min: u32,
+///     max: u32,
+///     name: &'static str,
+/// }
+///
+/// const fn double(value: u32) -> u32 {
+///     value * 2
+/// }
+///
+/// const DEFAULT: Limits = Limits {
+///     min: 0,
+///     max: 10,
+///     name: "default",
+/// };
+///
+/// const NAME: &str = "custom";
+///
+/// const CUSTOM: Limits = spread!(Limits {
+///     [double] max: 50,
+///     name: NAME,
+///     ..DEFAULT
+/// });
+///
+/// assert_eq!(CUSTOM.max, 100);
+/// ```
+///
+/// With the `tracing` or `log` feature enabled, `#![trace]` can be written at the start of the
+/// braces to log the constructed value at trace level with the crate of the same name, which must
+/// be a dependency of the calling crate. The value must implement `Debug`.
+///
+#[cfg_attr(any(feature = "tracing", feature = "log"), doc = "```rust")]
+#[cfg_attr(not(any(feature = "tracing", feature = "log")), doc = "```rust,ignore")]
+/// use spread_macros::spread;
+///
+/// #[derive(Debug)]
+/// struct Config {
+///     host: String,
+///     port: u16,
+/// }
+///
+/// let host = "localhost".to_string();
+/// let config = spread!(Config {
+///     #![trace]
+///     host,
+///     port: 8080,
+/// });
+/// ```
+///
+/// Raw identifiers can be used anywhere a field name is expected:
+///
+/// ```rust
+/// use spread_macros::spread;
+///
+/// struct Item {
+///     r#type: &'static str,
+///     r#fn: u32,
+/// }
+///
+/// struct Request {
+///     r#type: &'static str,
+///     r#fn: u32,
+///     r#async: bool,
+/// }
+///
+/// let item = Item { r#type: "item", r#fn: 42 };
+/// let r#async = true;
+/// let request = spread!(Request {
+///     r#async,
+///     { r#type, r#fn } in item,
+/// });
+///
+/// assert_eq!(request.r#type, "item");
+/// ```
 #[proc_macro]
 pub fn spread(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
     spread::spread(tokens)
@@ -147,6 +663,8 @@ pub fn spread(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
 /// The anonymous struct is automatically annotated with `#[derive(Copy, Clone, Debug, PartialEq, Eq)]`.
 /// If other attributes are needed they can be provided with outer attributes syntax `#![...]` before
 /// the fields.
+/// These default derives can be replaced with `#![derive_only(...)]`, which is useful when some
+/// fields don't implement one of those traits (like `f64` which is not `Eq`).
 ///
 /// ```rust
 /// use spread_macros::anon;
@@ -194,189 +712,1868 @@ pub fn spread(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
 /// let infered: u64 = anon.spread_into;
 /// let infered: u64 = anon.spread_clone_into;
 /// ```
-#[proc_macro]
-pub fn anon(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    anon::anon(tokens)
-}
-
-/// Allows to perform multiple `let` bindings with the same syntax as [`anon!`](crate::anon!),
-/// modifiers included. It is expected to be used in places where a lot of transformations are
-/// performed, such as lots of clones before moving values in a closure or async block.
 ///
-/// Each field name can be prefixed (before a potential modifier) but `mut` to perform a `let mut`
-/// binding.
+/// Alternatively, the type can be provided with `@ Type` after the field name (or with
+/// `field: Type = value` for fields with a value). The field then has this concrete type in the
+/// anonymous struct instead of a generic one, which thus must not contain elided lifetimes:
 ///
 /// ```rust
-/// use spread_macros::slet;
+/// use spread_macros::anon;
 ///
-/// #[derive(Clone, Debug, Default)]
-/// struct Bar {
-///     spread: u32,
-///     spread_ref: u32,
-///     spread_ref_mut: u32,
-///     spread_into: u32,
-///     spread_clone: u32,
-///     spread_clone_into: u32,
+/// let value = 42u32;
+///
+/// let anon = anon! {
+///     >value @ u64,
+///     >other @ u64: 42u32,
+///     >third: u64 = 42u32,
+///     name: &'static str = "anon",
+/// };
+///
+/// fn sum(values: &[u64]) -> u64 {
+///     values.iter().sum()
 /// }
 ///
-/// let mut bar = Bar::default();
-/// let name = 42u32;
-/// let name_ref = 42u32;
-/// let name_into = 42u32;
-/// let name_clone = 42u32;
-/// let name_clone_into = 42u32;
-/// let mut name_ref_mut = 42u32;
+/// assert_eq!(sum(&[anon.value, anon.other, anon.third]), 126);
+/// ```
 ///
-/// {
-///     slet! {
-///         name,
-///         mut &name_ref,
-///         &mut name_ref_mut,
-///         mut >name_into,
-///         +name_clone,
-///         mut +>name_clone_into,
-///         value: 42,
-///         {
-///             mut spread,
-///             &spread_ref,
-///             mut &mut spread_ref_mut,
-///             >spread_into,
-///             mut +spread_clone,
-///             +>spread_clone_into,
-///         } in &mut bar,
-///     };
+/// When the fields are wrapped in `struct Name { .. }` (with an optional visibility), a named
+/// struct is instead declared at item scope with a `Default` implementation using the provided
+/// values. No value is produced, as the macro is then an item: the value with the provided fields
+/// is `Name::default()`. Each field must be written as `field: Type = value` and have the
+/// visibility of the struct, while spread lists are not allowed. As the fields are likely to own
+/// their data, the struct is annotated with `#[derive(Clone, Debug, PartialEq, Eq)]`, without
+/// `Copy`. Fields can have outer attributes such as doc comments, which are emitted on the struct
+/// definition.
 ///
-///     // Fields with `>` (Into) needs to be used for their type to be inferred.
-///     let infered: u64 = name_into;
-///     let infered: u64 = name_clone_into;
-///     let infered: u64 = spread_into;
-///     let infered: u64 = spread_clone_into;
+/// ```rust
+/// use spread_macros::anon;
+///
+/// anon! {
+///     #![derive(Hash)]
+///     pub struct Settings {
+///         /// Name of the service.
+///         name: String = "default".to_string(),
+///         >port: u16 = 8080u16,
+///         retries: u32 = 3,
+///     }
+/// }
+///
+/// let defaults = Settings::default();
+/// assert_eq!(defaults.port, 8080);
+/// assert_eq!(defaults.retries, 3);
+///
+/// fn settings() -> Settings {
+///     Settings {
+///         retries: 5,
+///         ..Default::default()
+///     }
 /// }
+///
+/// assert_eq!(settings().name, "default");
+/// assert_eq!(settings().retries, 5);
 /// ```
-#[proc_macro]
-pub fn slet(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    slet::slet(tokens)
+///
+/// The default derives can be replaced with `#![derive_only(...)]`, for exemple when a field is a
+/// `f64` (which is not `Eq`) or a `String` (which is not `Copy`):
+///
+/// ```rust
+/// use spread_macros::anon;
+///
+/// let score = 4.2f64;
+/// let name = String::from("name");
+///
+/// let anon = anon! {
+///     #![derive_only(Clone, Debug, PartialEq)]
+///     score,
+///     name,
+/// };
+///
+/// assert_eq!(anon.clone(), anon);
+/// ```
+///
+/// Methods can be implemented on the struct with `#![impl { ... }]`. As fields without a provided
+/// type are generic, fields used in those methods should have a provided type.
+///
+/// ```rust
+/// use spread_macros::anon;
+///
+/// let a = 1u32;
+///
+/// let anon = anon! {
+///     #![impl {
+///         fn total(&self) -> u32 {
+///             self.a + self.b
+///         }
+///     }]
+///     a @ u32,
+///     b: u32 = 2,
+/// };
+///
+/// assert_eq!(anon.total(), 3);
+/// ```
+///
+/// A `Display` implementation printing `field = value` pairs can be generated with `#![display]`,
+/// which requires all the fields to implement `Display`. A custom format string can be provided
+/// with `#![display = "..."]`, in which fields can be used by name. Only the fields used in the
+/// format string are then bounded, by the formatting trait of their placeholder.
+///
+/// ```rust
+/// use spread_macros::anon;
+///
+/// let user = "admin";
+/// let id = 42u32;
+///
+/// let anon = anon! {
+///     #![display]
+///     user,
+///     id,
+/// };
+/// assert_eq!(anon.to_string(), "user = admin, id = 42");
+///
+/// let anon = anon! {
+///     #![display = "{user} (#{id:x}) {tags:?}"]
+///     user,
+///     id,
+///     tags: ["a", "b"],
+/// };
+/// assert_eq!(anon.to_string(), "admin (#2a) [\"a\", \"b\"]");
+/// ```
+///
+/// `PartialOrd` and `Ord` can be implemented with `#![ord(field1, field2)]`, comparing the listed
+/// fields in order. Fields that are not listed are then compared in declaration order, which keeps
+/// the ordering consistent with `Eq`.
+///
+/// ```rust
+/// use spread_macros::anon;
+///
+/// let mut users = vec![("bob", 2u32), ("alice", 2), ("carol", 1)];
+///
+/// users.sort_by_key(|&(name, age)| {
+///     anon! {
+///         #![ord(age)]
+///         name,
+///         age,
+///     }
+/// });
+///
+/// assert_eq!(users, vec![("carol", 1), ("alice", 2), ("bob", 2)]);
+/// ```
+///
+/// A field can itself be an anonymous struct with `field: { .. }`, supporting the same syntax.
+/// Braces that are a valid block are still parsed as a block, so a nested anonymous struct with
+/// a single field must have a trailing comma (`field: { inner, }`).
+///
+/// ```rust
+/// use spread_macros::anon;
+///
+/// let name = "localhost";
+/// let port = 8080u16;
+///
+/// let config = anon! {
+///     server: { name, >port },
+///     retries: 3u8,
+/// };
+///
+/// let port: u32 = config.server.port;
+/// assert_eq!(config.server.name, "localhost");
+/// assert_eq!(port, 8080);
+/// ```
+///
+/// The value can be converted into a named struct with the same fields with
+/// `#![into(Target)]`, which allows to use the anonymous struct syntax to fill structs
+/// from real APIs.
+///
+/// ```rust
+/// use spread_macros::anon;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct User {
+///     name: String,
+///     age: u32,
+/// }
+///
+/// let name = "Alice";
+///
+/// let user = anon! {
+///     #![into(User)]
+///     >name,
+///     age: 42,
+/// };
+/// assert_eq!(user, User { name: "Alice".to_string(), age: 42 });
+/// ```
+///
+/// When declaring a struct, `#![into(Target)]` implements `From<Name> for Target` instead.
+///
+/// As the macro doesn't know the fields of other anonymous structs, they can't be merged with
+/// `..other`. Their fields can however be listed in a spread list to be copied in the new
+/// anonymous struct. Only structs deriving [`AnonFields`] can be captured entirely with
+/// `anon!(..value)`.
+///
+/// ```rust
+/// use spread_macros::anon;
+///
+/// let base = anon! { id: 1u32, name: "base" };
+/// let extended = anon! {
+///     { id, name } in base,
+///     active: true,
+/// };
+///
+/// assert_eq!(extended.name, "base");
+/// assert!(extended.active);
+/// ```
+///
+/// `#![serde]` derives `Serialize` and `Deserialize` from the `serde` crate, which must be a
+/// dependency of the calling crate. Options can be provided with `#![serde(..)]`, which is also
+/// emitted as a container attribute. `#![serde(crate = "path")]` allows to use a re-exported
+/// `serde`.
+///
+/// ```rust
+/// use spread_macros::anon;
+///
+/// let user_id = 42u32;
+/// let anon = anon! {
+///     #![serde(rename_all = "camelCase")]
+///     user_id,
+/// };
+///
+/// assert_eq!(serde_json::to_string(&anon).unwrap(), r#"{"userId":42}"#);
+/// ```
+///
+/// `#![as trait Trait { .. }]` implements a trait of getters for the struct, allowing to return an
+/// anonymous struct as `impl Trait`. Each method must take `&self` and be named like a field.
+/// Methods returning a reference borrow the field (with `Borrow`), while other methods clone it
+/// and convert it with `Into`.
+///
+/// ```rust
+/// use spread_macros::anon;
+///
+/// trait Record {
+///     fn id(&self) -> u64;
+///     fn name(&self) -> &str;
+/// }
+///
+/// fn record(id: u32) -> impl Record {
+///     let name = format!("record-{id}");
+///     anon! {
+///         #![as trait Record {
+///             fn id(&self) -> u64;
+///             fn name(&self) -> &str;
+///         }]
+///         id,
+///         name,
+///     }
+/// }
+///
+/// let record = record(42);
+/// assert_eq!(record.id(), 42);
+/// assert_eq!(record.name(), "record-42");
+/// ```
+///
+/// `#![fields]` generates a `fields` method returning an iterator over the names and values (as
+/// `&dyn Debug`) of the fields, which allows to feed anonymous structs to structured loggers.
+///
+/// ```rust
+/// use spread_macros::anon;
+///
+/// let anon = anon! {
+///     #![fields]
+///     id: 42u32,
+///     name: "Alice",
+/// };
+///
+/// let fields: Vec<_> = anon
+///     .fields()
+///     .map(|(name, value)| format!("{name}={value:?}"))
+///     .collect();
+///
+/// assert_eq!(fields, vec!["id=42", "name=\"Alice\""]);
+/// ```
+///
+/// The anonymous struct is named `Anon`, which can be changed with `#![name = "Name"]` to make
+/// its `Debug` output and compilation errors easier to read.
+///
+/// ```rust
+/// use spread_macros::anon;
+///
+/// let anon = anon! {
+///     #![name = "RequestCtx"]
+///     id: 42u32,
+/// };
+///
+/// assert_eq!(format!("{anon:?}"), "RequestCtx { id: 42 }");
+/// ```
+///
+/// Other attributes such as `#![repr(C)]` or `#![repr(C, packed)]` are emitted on the struct,
+/// which allows to quickly assemble parameter blocks for FFI.
+///
+/// ```rust
+/// use spread_macros::anon;
+///
+/// let params = anon! {
+///     #![repr(C)]
+///     flags: 1u8,
+///     len: 2u32,
+/// };
+/// assert_eq!(core::mem::size_of_val(&params), 8);
+///
+/// let packed = anon! {
+///     #![repr(C, packed)]
+///     flags: 1u8,
+///     len: 2u32,
+/// };
+/// assert_eq!(core::mem::size_of_val(&packed), 5);
+/// ```
+///
+/// `#![by_ref]` takes a reference to every field without a modifier or a value, which allows to
+/// group large values into a view without moving or cloning them. Other modifiers such as
+/// `&mut` can still be used, and sources of spread lists should be borrowed.
+///
+/// ```rust
+/// use spread_macros::anon;
+///
+/// #[derive(Default)]
+/// struct Cache {
+///     entries: Vec<u32>,
+/// }
+///
+/// let name = "cache".to_string();
+/// let mut log = Vec::new();
+/// let cache = Cache::default();
+///
+/// let view = anon! {
+///     #![by_ref]
+///     name,
+///     &mut log,
+///     { entries } in &cache,
+/// };
+///
+/// view.log.push(view.entries.len());
+/// let _: &String = view.name;
+/// assert_eq!(log, vec![0]);
+/// ```
+#[proc_macro]
+pub fn anon(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    anon::anon(tokens)
 }
 
-/// Generates a struct representing the arguments of a given function or method, allowing to use
-/// Rust's struct update syntax, [`spread!`](crate::spread!) and `Default` with function arguments.
-/// The fields listed can use modifiers from [`spread!`] like `&`, which allows for exemple to call
-/// functions with reference arguments using a struct without references, which can thus implement
-/// `Default`.
+/// Allows to create an [`anon!`] with every field of a value of this struct with
+/// `anon!(..value)`, which is handy to snapshot a struct in tests. A `+`, `&` or `&mut` prefix
+/// (`anon!(..+value)`) applies the modifier to every field, borrowing the value instead of moving
+/// it. Fields of tuple structs are named `_0`, `_1`, etc.
+///
+/// `..value` must be the only content of the `anon!`, which doesn't support other fields nor
+/// options with it. The derive generates a hidden struct named `__SpreadMacrosAnon<Name>` next to
+/// the struct, with the same visibility.
+///
+/// ```rust
+/// use spread_macros::anon;
+///
+/// mod model {
+///     #[derive(spread_macros::AnonFields)]
+///     pub struct UserData {
+///         pub id: u32,
+///         pub name: String,
+///     }
+/// }
+///
+/// let mut user = model::UserData {
+///     id: 42,
+///     name: "Alice".to_string(),
+/// };
+///
+/// let snapshot = anon!(..+user);
+/// user.name.push_str(" Smith");
+///
+/// assert_eq!(snapshot.id, 42);
+/// assert_eq!(snapshot.name, "Alice");
+/// assert_eq!(format!("{snapshot:?}"), r#"Anon { id: 42, name: "Alice" }"#);
+///
+/// let fields = anon!(..&mut user);
+/// *fields.id += 1;
+/// assert_eq!(user.id, 43);
+/// ```
+#[proc_macro_derive(AnonFields)]
+pub fn anon_fields(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    anon_fields::anon_fields(tokens)
+}
+
+/// Allows to perform multiple `let` bindings with the same syntax as [`anon!`](crate::anon!),
+/// modifiers included. It is expected to be used in places where a lot of transformations are
+/// performed, such as lots of clones before moving values in a closure or async block.
+///
+/// Each field name can be prefixed (before a potential modifier) but `mut` to perform a `let mut`
+/// binding.
+///
+/// ```rust
+/// use spread_macros::slet;
+///
+/// #[derive(Clone, Debug, Default)]
+/// struct Bar {
+///     spread: u32,
+///     spread_ref: u32,
+///     spread_ref_mut: u32,
+///     spread_into: u32,
+///     spread_clone: u32,
+///     spread_clone_into: u32,
+/// }
+///
+/// let mut bar = Bar::default();
+/// let name = 42u32;
+/// let name_ref = 42u32;
+/// let name_into = 42u32;
+/// let name_clone = 42u32;
+/// let name_clone_into = 42u32;
+/// let mut name_ref_mut = 42u32;
+///
+/// {
+///     slet! {
+///         name,
+///         mut &name_ref,
+///         &mut name_ref_mut,
+///         mut >name_into,
+///         +name_clone,
+///         mut +>name_clone_into,
+///         value: 42,
+///         {
+///             mut spread,
+///             &spread_ref,
+///             mut &mut spread_ref_mut,
+///             >spread_into,
+///             mut +spread_clone,
+///             +>spread_clone_into,
+///         } in &mut bar,
+///     };
+///
+///     // Fields with `>` (Into) needs to be used for their type to be inferred.
+///     let infered: u64 = name_into;
+///     let infered: u64 = name_clone_into;
+///     let infered: u64 = spread_into;
+///     let infered: u64 = spread_clone_into;
+/// }
+/// ```
+///
+/// Alternatively, a type can be provided with `@ Type` (or `field: Type = value`), which is used
+/// as the type of the binding.
+///
+/// ```rust
+/// use spread_macros::slet;
+///
+/// fn parse(s: &str) -> u16 {
+///     s.parse().unwrap()
+/// }
+///
+/// let timeout = 30u32;
+/// let limits = (8u8, 16u8);
+///
+/// slet! {
+///     >timeout @ u64,
+///     port @ u16: parse("8080"),
+///     >retries: u32 = 3u8,
+///     { >0 as min @ u32, 1 as max } in limits,
+/// };
+///
+/// assert_eq!(timeout, 30u64);
+/// assert_eq!(port, 8080u16);
+/// assert_eq!(retries, 3u32);
+/// assert_eq!((min, max), (8u32, 16u8));
+/// ```
+///
+/// Fallible bindings can be written `pattern =? value else diverge`, which expands to
+/// `let pattern = value else { diverge };`.
+///
+/// ```rust
+/// use spread_macros::slet;
+///
+/// fn lookup(id: u32) -> Option<String> {
+///     (id == 42).then(|| "Alice".to_string())
+/// }
+///
+/// fn greet(id: u32, prefix: &String) -> Result<String, &'static str> {
+///     slet! {
+///         +prefix,
+///         Some(user) =? lookup(id) else return Err("not found"),
+///         Ok(count) =? "3".parse::<usize>() else {
+///             return Err("invalid count");
+///         },
+///     };
+///
+///     Ok(format!("{prefix} {user}{}", "!".repeat(count)))
+/// }
+///
+/// let prefix = "Hello".to_string();
+/// assert_eq!(greet(42, &prefix), Ok("Hello Alice!!!".to_string()));
+/// assert_eq!(greet(1, &prefix), Err("not found"));
+/// ```
+///
+/// Optional values can be flattened with the `?` modifier, which calls `unwrap_or_default`
+/// (`+?` clones the value first, which is needed for borrowed sources of non-`Copy` values).
+///
+/// ```rust
+/// use spread_macros::slet;
+///
+/// struct Config {
+///     timeout: Option<u64>,
+///     retries: Option<u32>,
+///     name: Option<String>,
+/// }
+///
+/// let cfg = Config {
+///     timeout: Some(30),
+///     retries: None,
+///     name: Some("service".to_string()),
+/// };
+///
+/// slet! { { ?timeout, ?retries, +?name } in &cfg };
+///
+/// assert_eq!(timeout, 30);
+/// assert_eq!(retries, 0);
+/// assert_eq!(name, "service");
+/// ```
+///
+/// The `+&` modifier clones `Arc` and `Rc` handles, making explicit that only the handle is cloned
+/// before moving it into a closure.
+///
+/// ```rust
+/// use {spread_macros::slet, std::{rc::Rc, sync::Arc}};
+///
+/// let data = Arc::new(vec![1, 2, 3]);
+/// let local = Rc::new("local");
+///
+/// let handle = {
+///     slet! { +&data, +&local };
+///     move || data.len() + local.len()
+/// };
+///
+/// assert_eq!(handle(), 8);
+/// assert_eq!(Arc::strong_count(&data), 2);
+/// ```
+///
+/// ```rust,compile_fail
+/// use spread_macros::slet;
+///
+/// let data = vec![1, 2, 3];
+/// slet! { +&data }; // not a shared pointer
+/// ```
+///
+/// `Weak` references can be upgraded with `^` (panicking when the value was dropped) or `^?`
+/// (returning early).
+///
+/// ```rust
+/// use {spread_macros::slet, std::rc::{Rc, Weak}};
+///
+/// fn title_len(parent: &Weak<String>) -> Option<usize> {
+///     slet! { ^?parent };
+///     Some(parent.len())
+/// }
+///
+/// let parent = Rc::new("window".to_string());
+/// let weak = Rc::downgrade(&parent);
+///
+/// {
+///     slet! { ^weak };
+///     assert_eq!(*weak, "window");
+/// }
+///
+/// assert_eq!(title_len(&weak), Some(6));
+/// drop(parent);
+/// assert_eq!(title_len(&weak), None);
+/// ```
+///
+/// When every field of a spread list only borrows or clones (`&`, `+`, `+>`, `&~`, `+~`, `+?`,
+/// `+&`, `^`, `^?` and `[path] &`), the source is borrowed instead of being moved, so it can still
+/// be used afterward without writing `in &source`.
+///
+/// ```rust
+/// use spread_macros::slet;
+///
+/// struct Config {
+///     name: String,
+///     port: u16,
+/// }
+///
+/// let config = Config {
+///     name: "server".to_string(),
+///     port: 8080,
+/// };
+///
+/// slet! { { +name, &port } in config };
+///
+/// assert_eq!(name, config.name);
+/// assert_eq!(*port, config.port);
+/// ```
+///
+/// Outer attributes written before an item are emitted on its `let` statement, such as `#[cfg]`
+/// or lint attributes. Attributes before a spread list apply to all its bindings.
+///
+/// ```rust
+/// use spread_macros::slet;
+///
+/// let buf = Vec::<u8>::new();
+/// let fixture = "fixture".to_string();
+///
+/// slet! {
+///     #[allow(unused_mut)]
+///     mut buf,
+///     #[cfg(not(test))]
+///     +fixture,
+/// };
+///
+/// assert!(buf.is_empty());
+/// ```
+///
+/// As the `?` prefix unwraps with `unwrap_or_default`, errors can instead be propagated with
+/// `try field: value`, which applies `?` to the value before other modifiers. `?field: value` and
+/// `field ?: value` are rejected by this macro to avoid confusing both.
+///
+/// ```rust
+/// use spread_macros::slet;
+///
+/// fn load(port: &str, name: &str) -> Result<(u64, String), std::num::ParseIntError> {
+///     slet! {
+///         try >port @ u64: port.parse::<u16>(),
+///         +name,
+///     };
+///
+///     Ok((port, name.to_string()))
+/// }
+///
+/// assert_eq!(load("8080", "server").unwrap(), (8080, "server".to_string()));
+/// assert!(load("invalid", "server").is_err());
+/// ```
+///
+/// Elements of tuples and arrays can be bound by position with `N as name` and `[N] name`
+/// respectively.
+///
+/// ```rust
+/// use spread_macros::slet;
+///
+/// let pair = (1u8, "two".to_string());
+/// let arr = ["a".to_string(), "b".to_string(), "c".to_string(), "d".to_string()];
+///
+/// slet! {
+///     { >0 as x @ u32, 1 as y } in pair,
+///     { [0] +first, [3] &fourth } in arr,
+/// };
+///
+/// assert_eq!((x, y.as_str()), (1, "two"));
+/// assert_eq!((first.as_str(), fourth.as_str()), ("a", "d"));
+/// ```
+///
+/// A fallback can be provided for an `Option` or `Result` source with `in source else fallback`.
+///
+/// ```rust
+/// use spread_macros::slet;
+///
+/// struct Config {
+///     host: &'static str,
+///     port: u16,
+/// }
+///
+/// const DEFAULT_CONFIG: Config = Config {
+///     host: "localhost",
+///     port: 8080,
+/// };
+///
+/// let maybe_config: Option<Config> = None;
+///
+/// slet! { { host, port } in maybe_config else DEFAULT_CONFIG };
+///
+/// assert_eq!((host, port), ("localhost", 8080));
+/// ```
+#[proc_macro]
+pub fn slet(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    slet::slet(tokens)
+}
+
+/// Create a `move` closure after performing [`slet!`] bindings, which allows to clone or convert
+/// the captured values inline, such as in argument position.
+///
+/// `sclosure!([bindings] move |args| body)` is equivalent to
+/// `{ slet! { bindings }; move |args| body }`.
+///
+/// ```rust
+/// use {spread_macros::sclosure, std::sync::Arc};
+///
+/// let prefix = "item".to_string();
+/// let separator = '-';
+/// let shared = Arc::new(vec![10, 20, 30]);
+///
+/// let labels: Vec<_> = (0..3)
+///     .map(sclosure!([+prefix, >separator @ String, +&shared] move |i| {
+///         format!("{prefix}{separator}{}", shared[i])
+///     }))
+///     .collect();
+///
+/// assert_eq!(labels, vec!["item-10", "item-20", "item-30"]);
+/// assert_eq!(prefix, "item");
+/// ```
+#[proc_macro]
+pub fn sclosure(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    sclosure::sclosure(tokens)
+}
+
+/// Create an `async move` block after performing [`slet!`] bindings, which allows to clone or
+/// convert the captured values inline, such as when spawning tasks.
+///
+/// `sasync!([bindings] { body })` is equivalent to `{ slet! { bindings }; async move { body } }`.
+/// The block can also be written `async move { body }`.
+///
+/// ```rust
+/// use {
+///     spread_macros::sasync,
+///     std::{future::Future, pin::pin, task::{Context, Poll, Waker}},
+/// };
+///
+/// fn block_on<F: Future>(future: F) -> F::Output {
+///     let mut future = pin!(future);
+///     let mut cx = Context::from_waker(Waker::noop());
+///     loop {
+///         if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+///             return output;
+///         }
+///     }
+/// }
+///
+/// let db = vec!["alice", "bob"];
+/// let req_id = 1u8;
+///
+/// let task = sasync!([+db, >req_id @ usize] { db[req_id].to_uppercase() });
+///
+/// assert_eq!(block_on(task), "BOB");
+/// assert_eq!(db.len(), 2);
+/// ```
+#[proc_macro]
+pub fn sasync(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    sasync::sasync(tokens)
+}
+
+/// Destructure the fields of a value (such as one created by [`anon!`]) into local bindings,
+/// with the same syntax as a spread list in [`slet!`].
+///
+/// `deanon!(value => { field1, field2 })` is equivalent to
+/// `slet! { { field1, field2 } in value }`, and supports the same modifiers, renaming and
+/// `mut` prefix.
+///
+/// ```rust
+/// use spread_macros::{anon, deanon};
+///
+/// let user = anon! { id: 42u32, name: "Alice", age: 30u8 };
+///
+/// deanon!(user => { mut id, name as username, >age @ u64 });
+///
+/// id += 1;
+/// assert_eq!(id, 43);
+/// assert_eq!(username, "Alice");
+/// assert_eq!(age, 30u64);
+/// ```
+#[proc_macro]
+pub fn deanon(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    deanon::deanon(tokens)
+}
+
+/// Generates a struct representing the arguments of a given function or method, allowing to use
+/// Rust's struct update syntax, [`spread!`](crate::spread!) and `Default` with function arguments.
+/// The fields listed can use modifiers from [`spread!`] like `&`, which allows for exemple to call
+/// functions with reference arguments using a struct without references, which can thus implement
+/// `Default`.
+/// ```rust
+/// use spread_macros::fn_struct;
+///
+/// fn foo(foo: u32, bar: u32, baz: &u32) -> u32 {
+///     foo + bar + baz
+/// }
+///
+/// fn_struct!(
+///     struct Foo
+///     for fn foo(
+///         one: u32 = 1,
+///         >two: u16 = 2, // converts from struct's u16 to functions u32
+///         &three: u32 = 3 // struct stores value, function takes reference
+///     ) -> u32
+/// );
+///
+/// let res = Foo {
+///     three: 33,
+///     ..Default::default()
+/// }
+/// .call();
+///
+/// assert_eq!(res, 1 + 2 + 33);
+/// ```
+///
+/// Note here that `res` is consumed when calling `call`. It can by reused if the name of the struct
+/// is prefixed with `&`, but it requires all the fields to either be `Copy`, passed by reference or
+/// cloned using `+` modifier (or `+>` to clone then convert it).
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// #
+/// # fn foo(foo: u32, bar: u32, baz: &u32) -> u32 {
+/// #     foo + bar + baz
+/// # }
+/// fn_struct!(
+///     struct &Foo
+///     for fn foo(
+///         one: u32 = 1,
+///         >two: u16 = 2,
+///         &three: u32 = 3
+///     ) -> u32
+/// );
+///
+/// let args = Foo {
+///     three: 33,
+///     ..Default::default()
+/// };
+/// args.call();
+/// args.call();
+/// ```
+///
+/// When some fields have a value, the ones without one use `Default::default()` in the `Default`
+/// implementation.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn greet(name: &str, greeting: &str) -> String {
+///     format!("{greeting}, {name}!")
+/// }
+///
+/// fn_struct!(
+///     struct Greet
+///     for fn greet(
+///         &name: String,
+///         &greeting: String = "Hello".to_string()
+///     ) -> String
+/// );
+///
+/// assert_eq!(Greet::default().call(), "Hello, !");
+/// let greet = Greet { name: "World".into(), ..Default::default() };
+/// assert_eq!(greet.call(), "Hello, World!");
+/// ```
+///
+/// Default values are evaluated in declaration order and can use the previous fields.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn rect(width: u32, height: u32, area: u32) -> String {
+///     format!("{width}x{height} ({area})")
+/// }
+///
+/// fn_struct!(
+///     struct Rect
+///     for fn rect(
+///         width: u32 = 5,
+///         height: u32 = 10,
+///         area: u32 = width * height
+///     ) -> String
+/// );
+///
+/// assert_eq!(Rect::default().call(), "5x10 (50)");
+/// ```
+///
+/// A field declared as `field: Option<T> ?= fallback` is stored as an `Option` (which defaults to
+/// `None`), and the function receives `fallback` if it is `None` when calling, which models
+/// optional keyword arguments.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// use std::time::Duration;
+///
+/// const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+///
+/// fn request(url: &str, timeout: Duration) -> String {
+///     format!("{url} ({}s)", timeout.as_secs())
+/// }
+///
+/// fn_struct!(
+///     struct &Request
+///     for fn request(
+///         &url: String = "localhost".to_string(),
+///         timeout: Option<Duration> ?= DEFAULT_TIMEOUT
+///     ) -> String
+/// );
+///
+/// assert_eq!(Request::default().call(), "localhost (30s)");
+///
+/// let request = Request {
+///     timeout: Some(Duration::from_secs(5)),
+///     ..Default::default()
+/// };
+/// assert_eq!(request.call(), "localhost (5s)");
+/// ```
+///
+/// The `setters` option generates a chainable `with_<field>` setter for each field, which avoids
+/// spelling out struct-update syntax.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// #
+/// # fn foo(foo: u32, bar: u32, baz: &u32) -> u32 {
+/// #     foo + bar + baz
+/// # }
+/// fn_struct!(
+///     #[fn_struct(setters)]
+///     struct Foo
+///     for fn foo(
+///         one: u32 = 1,
+///         >two: u16 = 2,
+///         &three: u32 = 3
+///     ) -> u32
+/// );
+///
+/// assert_eq!(Foo::default().with_three(33).call(), 1 + 2 + 33);
+/// ```
+///
+/// The `new` option generates a `new` constructor taking the fields without a `= value` or
+/// `?= fallback`, the other fields having their default value. It is always generated for
+/// `#[non_exhaustive]` structs, as it allows to expose a struct and later add fields with default
+/// values without breaking callers.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn connect(host: &str, port: u16, retries: u32) -> String {
+///     format!("{host}:{port} ({retries} retries)")
+/// }
+///
+/// fn_struct!(
+///     #[non_exhaustive]
+///     #[fn_struct(setters)]
+///     pub struct Connect
+///     for fn connect(&host: String, port: u16 = 80, retries: u32 = 3) -> String
+/// );
+///
+/// let connect = Connect::new("localhost".into()).with_port(8080);
+/// assert_eq!(connect.call(), "localhost:8080 (3 retries)");
+/// ```
+///
+/// With the `tuple` option, the struct can be converted from and into a tuple of its fields, in
+/// declaration order.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// #
+/// # fn foo(foo: u32, bar: u32, baz: &u32) -> u32 {
+/// #     foo + bar + baz
+/// # }
+/// fn_struct!(
+///     #[fn_struct(tuple)]
+///     struct Foo
+///     for fn foo(
+///         one: u32,
+///         >two: u16,
+///         &three: u32
+///     ) -> u32;
+///
+///     #[fn_struct(tuple)]
+///     struct VecPush<T: Clone>
+///     for fn Vec::<T>::push(
+///         &mut self,
+///         value: T
+///     )
+/// );
+///
+/// let foo: Foo = (1, 2, 3).into();
+/// let (one, two, three) = foo.into();
+/// assert_eq!((one, two, three), (1, 2, 3));
+///
+/// let push = VecPush::from(("a",));
+/// let (value,): (&str,) = push.into();
+/// assert_eq!(value, "a");
+/// ```
+///
+/// When the fields of a struct (names and types) are a subset of the fields of another struct of
+/// the same invocation, the larger struct can be converted into the smaller one, and the smaller
+/// one into the larger one if it implements `Default`, which provides the missing fields. Generic
+/// structs are not converted.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn render(text: &str, width: usize, fill: char) -> String {
+///     let padding = width.saturating_sub(text.len());
+///     format!("{text}{}", fill.to_string().repeat(padding))
+/// }
+///
+/// fn render_default(text: &str) -> String {
+///     render(text, 8, '.')
+/// }
+///
+/// fn_struct!(
+///     #[fn_struct(new)]
+///     struct Render
+///     for fn render(&text: String, width: usize = 8, fill: char = '.') -> String;
+///
+///     struct RenderDefault
+///     for fn render_default(&text: String) -> String
+/// );
+///
+/// let render: Render = RenderDefault { text: "ab".into() }.into();
+/// assert_eq!(Render { fill: '-', ..render }.call(), "ab------");
+///
+/// let render_default = RenderDefault::from(Render::new("cd".into()));
+/// assert_eq!(render_default.call(), "cd......");
+/// ```
+///
+/// The `display_call` option generates `display_call`, which renders the call that would be
+/// performed using the `Debug` representation of the fields, which is useful for logging or dry-run
+/// modes. All the fields must implement `Debug`.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn greet(name: &str, times: u32) -> String {
+///     format!("Hello {name}!").repeat(times as usize)
+/// }
+///
+/// fn_struct!(
+///     #[fn_struct(display_call)]
+///     struct Greet
+///     for fn greet(&name: String, times: u32) -> String
+/// );
+///
+/// let greet = Greet { name: "World".into(), times: 2 };
+/// assert_eq!(greet.display_call(), r#"greet("World", 2)"#);
+/// ```
+///
+/// The `metadata` option generates the `ARG_NAMES`, `ARG_COUNT` and `FN_PATH` constants, which
+/// describe the called function and are useful for tools built on top of the generated structs.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn greet(times: u32, name: &str) {}
+///
+/// fn_struct!(
+///     #[fn_struct(metadata)]
+///     struct Greet
+///     for fn greet(&name: String, #0 times: u32)
+/// );
+///
+/// assert_eq!(Greet::ARG_NAMES, ["times", "name"]);
+/// assert_eq!(Greet::ARG_COUNT, 2);
+/// assert_eq!(Greet::FN_PATH, "greet");
+/// ```
+///
+/// The `check_signature` option checks the signature of the function against the types of the
+/// fields, which reports changes of the function on its path in the macro invocation. It requires
+/// a non-`async` function and fields without modifiers or fallbacks, whose types are exactly the
+/// types of the parameters (no coercion is applied, such as `&String` to `&str`).
+/// ```rust,compile_fail
+/// # use spread_macros::fn_struct;
+/// fn scale(value: u32, factor: u16) -> u32 {
+///     value * factor as u32
+/// }
+///
+/// fn_struct!(
+///     #[fn_struct(check_signature)]
+///     struct Scale
+///     for fn scale(value: u32, factor: u32) -> u32 // error: expected `fn(u32, u32) -> u32`
+/// );
+/// ```
+///
+/// The `patch` option generates a `<Name>Patch` struct with optional fields. It can be passed to
+/// `call_with` to only override the `Some` fields before calling, which is handy for table-driven
+/// tests.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// #
+/// # fn foo(foo: u32, bar: u32, baz: &u32) -> u32 {
+/// #     foo + bar + baz
+/// # }
+/// fn_struct!(
+///     #[fn_struct(patch)]
+///     struct Foo
+///     for fn foo(
+///         one: u32 = 1,
+///         >two: u16 = 2,
+///         &three: u32 = 3
+///     ) -> u32
+/// );
+///
+/// let cases = [
+///     (FooPatch::default(), 6),
+///     (FooPatch { one: Some(10), ..Default::default() }, 15),
+///     (FooPatch { two: Some(20), three: Some(30), ..Default::default() }, 51),
+/// ];
+///
+/// for (patch, expected) in cases {
+///     assert_eq!(Foo::default().call_with(patch), expected);
+/// }
+/// ```
+///
+/// The struct can be generic over the types of the function arguments, while the `call`
+/// function can also be generic over types not appearing in the arguments.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn_struct!(
+///     // `T` must be listed here as one of the arguments use it.
+///     struct &VecPush<T: Clone>
+///     for fn Vec::<T>::push(
+///         &mut self,
+///         +value: T
+///     );
+///
+///     // note that we can declare multiple structs in a single macro call by
+///     // separating them with `;`.
+///
+///     struct &VecPop
+///     // `T` must be listed here as no arguments use it but it is still used by the function.
+///     for<T> fn Vec::<T>::pop(
+///         &mut self,
+///     ) -> Option<T>
+/// );
+///
+/// let mut list = vec![1, 2, 3, 4];
+///
+/// let pop = VecPop { };
+/// pop.call(&mut list);
+/// pop.call(&mut list);
+/// assert_eq!(&list, &[1, 2]);
+///
+/// let push = VecPush { value: 10 };
+/// push.call(&mut list);
+/// push.call(&mut list);
+/// assert_eq!(&list, &[1, 2, 10, 10]);
+/// ```
+///
+/// Fields are passed to the function in the order they are listed, unless they are prefixed with
+/// `#N` to pass them as the `N`-th argument (starting at 0, and not counting `self` when it is a
+/// parameter of `call`). It allows the fields of the struct to be in a different order than the
+/// function arguments.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn sub(left: u32, right: u32) -> u32 {
+///     left - right
+/// }
+///
+/// fn_struct!(
+///     struct Sub
+///     for fn sub(
+///         #1 right: u32,
+///         left: u32
+///     ) -> u32
+/// );
+///
+/// assert_eq!(Sub { right: 1, left: 3 }.call(), 2);
+/// ```
+///
+/// Fields can also be annotated with attributes (like doc comments or `#[serde(default)]`), which
+/// are forwarded to the fields of the generated struct.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn connect(host: &str, port: u16) -> String {
+///     format!("{host}:{port}")
+/// }
+///
+/// fn_struct!(
+///     #[derive(Debug)]
+///     struct Connect
+///     for fn connect(
+///         /// Host to connect to
+///         &host: String = "localhost".to_string(),
+///         /// Port to connect to
+///         #[allow(unused)]
+///         port: u16 = 8080
+///     ) -> String
+/// );
+///
+/// assert_eq!(Connect::default().call(), "localhost:8080");
+/// ```
+///
+/// Fields are private by default, and can be given a visibility (before `#N` positions and
+/// modifiers), which also applies to the fields of the `<Name>Patch` struct of the `patch`
+/// option.
+/// ```rust
+/// mod api {
+///     # use spread_macros::fn_struct;
+///     pub fn connect(host: &str, port: u16) -> String {
+///         format!("{host}:{port}")
+///     }
+///
+///     fn_struct!(
+///         pub struct Connect
+///         for fn connect(
+///             pub &host: String = "localhost".to_string(),
+///             pub(crate) port: u16 = 8080
+///         ) -> String
+///     );
+/// }
+///
+/// let connect = api::Connect { port: 80, ..Default::default() };
+/// assert_eq!(connect.call(), "localhost:80");
+/// ```
+///
+/// Giving a type to `self` stores the receiver in the struct as a `receiver` field instead of
+/// taking it as a parameter of `call`. It supports the same modifiers as other fields, and the
+/// stored receiver can be any type that derefs to the expected one (like an `Arc`).
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// use std::sync::Arc;
+///
+/// fn_struct!(
+///     struct Push
+///     for fn Vec::<u32>::push(
+///         &mut self: Vec<u32>,
+///         value: u32
+///     ) -> ();
+///
+///     struct &Len
+///     for fn Vec::<u32>::len(
+///         &self: Arc<Vec<u32>>
+///     ) -> usize
+/// );
+///
+/// Push { receiver: vec![1, 2], value: 3 }.call();
+///
+/// let len = Len { receiver: Arc::new(vec![1, 2, 3]) };
+/// assert_eq!(len.call(), 3);
+/// ```
+///
+/// With a `[path]` modifier, the stored receiver is passed to `path`, which can return a guard
+/// (like a `MutexGuard`) that is held during the call and dereferenced to `&T` or `&mut T`.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// use std::sync::{Arc, Mutex, MutexGuard};
+///
+/// fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+///     mutex.lock().unwrap()
+/// }
+///
+/// fn_struct!(
+///     struct &Push
+///     for fn Vec::<u32>::push(
+///         [lock] &self: Arc<Mutex<Vec<u32>>>,
+///         value: u32
+///     ) -> ()
+/// );
+///
+/// let list = Arc::new(Mutex::new(vec![1, 2]));
+/// Push { receiver: Arc::clone(&list), value: 3 }.call();
+/// assert_eq!(*list.lock().unwrap(), [1, 2, 3]);
+/// ```
+///
+/// Receivers such as `self: Arc<Self>`, `self: Box<Self>` or `self: Pin<&mut Self>` are taken as
+/// a parameter of `call`, as their type mentions `Self`.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// use std::{pin::Pin, sync::Arc};
+///
+/// struct Counter(u32);
+///
+/// impl Counter {
+///     fn add_arc(self: Arc<Self>, value: u32) -> u32 {
+///         self.0 + value
+///     }
+///
+///     fn add_boxed(self: Box<Self>, value: u32) -> u32 {
+///         self.0 + value
+///     }
+///
+///     fn add_pinned(mut self: Pin<&mut Self>, value: u32) -> u32 {
+///         self.0 += value;
+///         self.0
+///     }
+/// }
+///
+/// fn_struct!(
+///     struct &AddArc
+///     for fn Counter::add_arc(self: Arc<Self>, value: u32) -> u32;
+///
+///     struct &AddBoxed
+///     for fn Counter::add_boxed(self: Box<Self>, value: u32) -> u32;
+///
+///     struct &AddPinned
+///     for fn Counter::add_pinned(self: Pin<&mut Self>, value: u32) -> u32
+/// );
+///
+/// assert_eq!(AddArc { value: 2 }.call(Arc::new(Counter(1))), 3);
+/// assert_eq!(AddBoxed { value: 2 }.call(Box::new(Counter(1))), 3);
+///
+/// let mut counter = Counter(1);
+/// assert_eq!(AddPinned { value: 2 }.call(Pin::new(&mut counter)), 3);
+/// ```
+///
+/// Other arguments can also be provided when calling by marking them with `@call`. They are
+/// parameters of `call` (after `self`) instead of fields, and are passed to the function at their
+/// position. They support modifiers, but not default values, attributes or `#N` positions.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// use std::collections::HashMap;
+///
+/// fn_struct!(
+///     struct Insert
+///     for fn HashMap::<String, u32>::insert(&mut self, @call >key: &str, value: u32) -> Option<u32>
+/// );
+///
+/// let insert = Insert { value: 1 };
+/// let mut map = HashMap::new();
+/// insert.call(&mut map, "a");
+/// assert_eq!(map["a"], 1);
+/// ```
+///
+/// Fields can be validated with `#[validate(predicate, "message")]`, where `predicate` is called
+/// with a reference to the field. It generates a `validate` method returning the message of the
+/// first failing predicate, and a `call_validated` method which only calls the function if the
+/// validation succeeds.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn listen(host: &str, port: u16) -> String {
+///     format!("{host}:{port}")
+/// }
+///
+/// fn_struct!(
+///     struct Listen
+///     for fn listen(
+///         #[validate(|host| !host.is_empty(), "host must not be empty")]
+///         &host: String,
+///         #[validate(|port| *port != 0, "port must be non-zero")]
+///         port: u16
+///     ) -> String
+/// );
+///
+/// let listen = Listen { host: "localhost".into(), port: 0 };
+/// assert_eq!(listen.validate(), Err("`port`: port must be non-zero".to_string()));
+///
+/// let listen = Listen { host: "localhost".into(), port: 8080 };
+/// assert_eq!(listen.call_validated(), Ok("localhost:8080".to_string()));
+/// ```
+///
+/// Const generics and default type parameters are supported as well.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn sum<const N: usize>(values: [u32; N]) -> u32 {
+///     values.iter().sum()
+/// }
+///
+/// fn describe<T: std::fmt::Debug>(value: T) -> String {
+///     format!("{value:?}")
+/// }
+///
+/// fn_struct!(
+///     struct Sum<const N: usize>
+///     for fn sum::<N>(values: [u32; N]) -> u32;
+///
+///     struct Describe<T: std::fmt::Debug = u32>
+///     for fn describe(value: T) -> String
+/// );
+///
+/// assert_eq!(Sum { values: [1, 2, 3] }.call(), 6);
+///
+/// let describe: Describe = Describe { value: 3 };
+/// assert_eq!(describe.call(), "3");
+/// ```
+///
+/// Fields with an `impl Trait` type (as found in many function signatures) are turned into new
+/// generic parameters of the struct.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn shout(text: impl AsRef<str>, times: usize) -> String {
+///     text.as_ref().to_uppercase().repeat(times)
+/// }
+///
+/// fn_struct!(
+///     struct Shout
+///     for fn shout(text: impl AsRef<str>, times: usize) -> String
+/// );
+///
+/// assert_eq!(Shout { text: "hey", times: 2 }.call(), "HEYHEY");
+/// assert_eq!(Shout { text: String::from("ho"), times: 1 }.call(), "HO");
+/// ```
+///
+/// Similarly, lifetimes used in field types that are not declared are added to the generics of
+/// the struct, while elided lifetimes of references are given names.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn longest<'a>(left: &'a str, right: &'a str, separator: &str) -> &'a str {
+///     if left.len() >= right.len() { left } else { right }
+/// }
+///
+/// fn_struct!(
+///     struct Longest
+///     for fn longest(left: &'a str, right: &'a str, separator: &str) -> &'a str
+/// );
+///
+/// let right = String::from("world!");
+/// let longest = Longest { left: "hello", right: &right, separator: " " }.call();
+/// assert_eq!(longest, "world!");
+/// ```
+///
+/// Trait methods can be targeted with a fully qualified path `<Type as Trait>::method`, in which
+/// case `call` requires `Type: Trait`. It allows the trait to be generic over parameters of the
+/// struct, and `Type` or generic parameters of the method to be parameters of `call` which can be
+/// provided with a turbofish.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// trait Encode<T> {
+///     fn encode<O: From<T>>(&self, value: T) -> O;
+/// }
+///
+/// struct Identity;
+///
+/// impl Encode<u8> for Identity {
+///     fn encode<O: From<u8>>(&self, value: u8) -> O {
+///         O::from(value)
+///     }
+/// }
+///
+/// fn_struct!(
+///     struct &Encoded<T: Clone>
+///     for<E, O: From<T>> fn <E as Encode<T>>::encode::<O>(&self, +value: T) -> O
+/// );
+///
+/// let encoded = Encoded { value: 42u8 };
+/// assert_eq!(encoded.call::<Identity, u64>(&Identity), 42u64);
+/// ```
+///
+/// Struct can be annotated with usual derives and attributes by writing them at the start.
+/// Unless a doc comment is provided, the generated struct is documented with the target function,
+/// the default values and a usage example.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn_struct!(
+///     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///     pub struct VecPush<T: Clone>
+///     for fn Vec::<T>::push(
+///         &mut self,
+///         value: T
+///     )
+/// );
+/// ```
+///
+/// Options of the macro are provided with `#[fn_struct(..)]` attributes:
+/// - `impl Trait`: implements a trait defined by the user, which must have an `Output` associated
+///   type and a `call` method with the same receiver as the generated `call` method (`self` or
+///   `&self` for `&Name` structs). It allows code to be generic over any prepared call.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// trait CallFn {
+///     type Output;
+///     fn call(self) -> Self::Output;
+/// }
+///
+/// fn add(a: u32, b: u32) -> u32 {
+///     a + b
+/// }
+///
+/// fn concat(a: &str, b: &str) -> String {
+///     format!("{a}{b}")
+/// }
+///
+/// fn_struct!(
+///     #[fn_struct(impl CallFn)]
+///     struct Add
+///     for fn add(a: u32, b: u32) -> u32;
+///
+///     #[fn_struct(impl CallFn)]
+///     struct Concat
+///     for fn concat(&a: String, &b: String) -> String
+/// );
+///
+/// fn run<C: CallFn>(call: C) -> C::Output {
+///     call.call()
+/// }
+///
+/// assert_eq!(run(Add { a: 1, b: 2 }), 3);
+/// assert_eq!(run(Concat { a: "a".into(), b: "b".into() }), "ab");
+/// ```
+/// - `arbitrary`: implements `proptest::arbitrary::Arbitrary` using `any` for each field, to
+///   allow calling the function with random arguments in property tests. It requires the
+///   `proptest` feature, `proptest` must be a dependency of the calling crate, and the struct must
+///   implement `Debug`.
+#[cfg_attr(feature = "proptest", doc = "```rust")]
+#[cfg_attr(not(feature = "proptest"), doc = "```rust,ignore")]
+/// use {proptest::prelude::*, spread_macros::fn_struct};
+///
+/// fn add(a: u32, b: u32) -> u64 {
+///     a as u64 + b as u64
+/// }
+///
+/// fn_struct!(
+///     #[derive(Debug)]
+///     #[fn_struct(arbitrary)]
+///     struct Add
+///     for fn add(a: u32, b: u32) -> u64
+/// );
+///
+/// proptest!(|(args in any::<Add>())| {
+///     let sum = Add { ..args }.call();
+///     prop_assert!(sum >= args.a as u64);
+/// });
+/// ```
+/// - `cached`: generates `call_cached`, which memoizes results in a static cache, and
+///   `call_cached_in`, which uses a provided `HashMap` (only the latter is available for generic
+///   structs). The struct must implement `Hash`, `Eq` and `Clone`, and the output `Clone`.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn fibonacci(n: u64) -> u64 {
+///     match n {
+///         0 | 1 => n,
+///         n => Fibonacci { n: n - 1 }.call_cached() + Fibonacci { n: n - 2 }.call_cached(),
+///     }
+/// }
+///
+/// fn_struct!(
+///     #[derive(Clone, PartialEq, Eq, Hash)]
+///     #[fn_struct(cached)]
+///     struct Fibonacci
+///     for fn fibonacci(n: u64) -> u64
+/// );
+///
+/// assert_eq!(Fibonacci { n: 80 }.call_cached(), 23416728348467685);
+///
+/// let mut cache = std::collections::HashMap::new();
+/// assert_eq!(Fibonacci { n: 10 }.call_cached_in(&mut cache), 55);
+/// assert_eq!(cache.len(), 1);
+/// ```
+/// - `spawn`: generates `spawn` for `async` functions, which spawns a `tokio` task performing the
+///   call, or `spawn_blocking` for other functions, which performs the call on the blocking thread
+///   pool of `tokio`. Both return the `JoinHandle` of the task. It requires the `tokio` feature and
+///   `tokio` must be a dependency of the calling crate.
+#[cfg_attr(feature = "tokio", doc = "```rust")]
+#[cfg_attr(not(feature = "tokio"), doc = "```rust,ignore")]
+/// use spread_macros::fn_struct;
+///
+/// fn checksum(data: &[u8]) -> u32 {
+///     data.iter().map(|&byte| byte as u32).sum()
+/// }
+///
+/// async fn fetch(url: &str) -> String {
+///     format!("content of {url}")
+/// }
+///
+/// fn_struct!(
+///     #[fn_struct(spawn)]
+///     struct Checksum
+///     for fn checksum(&data: Vec<u8>) -> u32;
+///
+///     #[fn_struct(spawn)]
+///     struct Fetch
+///     for async fn fetch(&url: String) -> String
+/// );
+///
+/// #[tokio::main]
+/// async fn main() {
+///     let checksum = Checksum { data: vec![1, 2, 3] }.spawn_blocking();
+///     let content = Fetch { url: "localhost".into() }.spawn();
+///     assert_eq!(checksum.await.unwrap(), 6);
+///     assert_eq!(content.await.unwrap(), "content of localhost");
+/// }
+/// ```
+/// - `instrument`: calls the function inside a `tracing` span named after the function, which
+///   records the `Debug` representation of each field. Fields can be excluded with
+///   `instrument(skip(field1, field2))`. It requires the `tracing` feature and `tracing` must be a
+///   dependency of the calling crate.
+#[cfg_attr(feature = "tracing", doc = "```rust")]
+#[cfg_attr(not(feature = "tracing"), doc = "```rust,ignore")]
+/// use spread_macros::fn_struct;
+///
+/// fn login(user: &str, password: &str) -> bool {
+///     tracing::info!("logging in"); // recorded with `user` but not `password`
+///     !user.is_empty() && !password.is_empty()
+/// }
+///
+/// fn_struct!(
+///     #[fn_struct(instrument(skip(password)))]
+///     struct Login
+///     for fn login(&user: String, &password: String) -> bool
+/// );
+///
+/// assert!(Login { user: "admin".into(), password: "hunter2".into() }.call());
+/// ```
+/// - `calls(self, &self, &mut self)`: generates the listed call variants instead of a single
+///   `call`, respectively named `call`, `call_ref` and `call_mut`. The first variant is the one
+///   used by the other generated methods (`try_call`, `call_with`, trait impls, ...). It can't be
+///   combined with `&Name`, and `&self` is not allowed with fields passed by `&mut`.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// fn push(list: &mut Vec<u32>, value: u32) -> usize {
+///     list.push(value);
+///     list.len()
+/// }
+///
+/// fn_struct!(
+///     #[fn_struct(calls(&mut self, self))]
+///     struct Push
+///     for fn push(&mut list: Vec<u32>, value: u32) -> usize
+/// );
+///
+/// let mut push = Push { list: vec![], value: 7 };
+/// assert_eq!(push.call_mut(), 1);
+/// assert_eq!(push.call_mut(), 2);
+/// assert_eq!(push.call(), 3);
+/// ```
+/// - `call_attr(attr1, attr2)`: adds attributes to the `call` methods, such as `inline`,
+///   `must_use` or `track_caller`, the latter making panics of `#[track_caller]` functions report
+///   the caller of `call`.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// use std::panic::Location;
+///
+/// #[track_caller]
+/// fn location(_name: &str) -> &'static Location<'static> {
+///     Location::caller()
+/// }
+///
+/// fn_struct!(
+///     #[fn_struct(call_attr(track_caller, must_use))]
+///     struct Here
+///     for fn location(&name: String) -> &'static Location<'static>
+/// );
+///
+/// let here = Here { name: "here".into() }.call();
+/// assert_eq!(here.line(), line!() - 1);
+/// ```
+/// - `new`: generates a `new` constructor taking the fields without default value, see above.
+/// - `patch`: generates a `<Name>Patch` struct and `call_with`, see above.
+/// - `check_signature`: checks the signature of the function against the types of the fields,
+///   see above.
+/// - `tuple`: implements `From` conversions from and into a tuple of the fields, see above.
+/// - `display_call`: generates `display_call` rendering the call with `Debug`, see above.
+/// - `setters`: generates chainable `with_<field>` setters, see above.
+/// - `metadata`: generates the `ARG_NAMES`, `ARG_COUNT` and `FN_PATH` constants, see above.
+/// - `serde`: derives `serde::Serialize` and `serde::Deserialize` for this struct only, which
+///   allows to persist prepared calls. `serde` with its `derive` feature must be a dependency of the
+///   calling crate.
 /// ```rust
 /// use spread_macros::fn_struct;
 ///
-/// fn foo(foo: u32, bar: u32, baz: &u32) -> u32 {
-///     foo + bar + baz
+/// fn send(to: &str, subject: &str) -> bool {
+///     !to.is_empty() && !subject.is_empty()
 /// }
 ///
 /// fn_struct!(
-///     struct Foo
-///     for fn foo(
-///         one: u32 = 1,
-///         >two: u16 = 2, // converts from struct's u16 to functions u32
-///         &three: u32 = 3 // struct stores value, function takes reference
-///     ) -> u32
+///     #[fn_struct(serde)]
+///     struct Send
+///     for fn send(&to: String, &subject: String) -> bool
 /// );
 ///
-/// let res = Foo {
-///     three: 33,
-///     ..Default::default()
+/// let job = serde_json::to_string(&Send { to: "a@b.c".into(), subject: "hi".into() }).unwrap();
+/// let send: Send = serde_json::from_str(&job).unwrap();
+/// assert!(send.call());
+/// ```
+///
+/// With the `nightly` feature enabled, structs whose `call` takes no parameters also implement
+/// `FnOnce<()>` (and `FnMut<()>`/`Fn<()>` for `&Name` structs), which allows to pass them directly
+/// to APIs expecting closures. It requires a nightly compiler and
+/// `#![feature(unboxed_closures, fn_traits)]` in the calling crate.
+#[cfg_attr(feature = "nightly", doc = "```rust")]
+#[cfg_attr(not(feature = "nightly"), doc = "```rust,ignore")]
+/// #![feature(unboxed_closures, fn_traits)]
+/// # use spread_macros::fn_struct;
+/// fn add(a: u32, b: u32) -> u32 {
+///     a + b
 /// }
-/// .call();
 ///
-/// assert_eq!(res, 1 + 2 + 33);
+/// fn_struct!(
+///     struct &Add
+///     for fn add(a: u32, b: u32) -> u32
+/// );
+///
+/// let add = Add { a: 1, b: 2 };
+/// let results: Vec<u32> = (0..2).map(|_| add()).collect();
+/// assert_eq!(results, [3, 3]);
 /// ```
 ///
-/// Note here that `res` is consumed when calling `call`. It can by reused if the name of the struct
-/// is prefixed with `&`, but it requires all the fields to either be `Copy`, passed by reference or
-/// cloned using `+` modifier (or `+>` to clone then convert it).
+/// Async functions are supported with `for async fn`, in which case `call` is also async and
+/// awaits the function.
 /// ```rust
 /// # use spread_macros::fn_struct;
-/// #
-/// # fn foo(foo: u32, bar: u32, baz: &u32) -> u32 {
-/// #     foo + bar + baz
+/// async fn fetch(url: &str, retries: u32) -> String {
+///     format!("{url} ({retries} retries)")
+/// }
+///
+/// fn_struct!(
+///     struct Fetch
+///     for async fn fetch(
+///         &url: String = "localhost".to_string(),
+///         retries: u32 = 3
+///     ) -> String
+/// );
+///
+/// # fn block_on<F: std::future::Future>(future: F) -> F::Output {
+/// #     let mut future = std::pin::pin!(future);
+/// #     let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+/// #     loop {
+/// #         if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+/// #             return output;
+/// #         }
+/// #     }
 /// # }
+/// let res = block_on(Fetch::default().call());
+/// assert_eq!(res, "localhost (3 retries)");
+/// ```
+///
+/// Similarly, `for const fn` generates a `const fn call`, which only allows the `&`, `&mut` and
+/// `[path]` modifiers (with `path` being a `const fn`).
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// const fn area(width: u32, height: &u32) -> u32 {
+///     width * *height
+/// }
+///
 /// fn_struct!(
-///     struct &Foo
-///     for fn foo(
-///         one: u32 = 1,
-///         >two: u16 = 2,
-///         &three: u32 = 3
+///     struct Area
+///     for const fn area(
+///         width: u32,
+///         &height: u32
 ///     ) -> u32
 /// );
 ///
-/// let args = Foo {
-///     three: 33,
-///     ..Default::default()
-/// };
-/// args.call();
-/// args.call();
+/// const AREA: u32 = Area { width: 2, height: 3 }.call();
+/// assert_eq!(AREA, 6);
 /// ```
 ///
-/// The struct can be generic over the types of the function arguments, while the `call`
-/// function can also be generic over types not appearing in the arguments.
+/// `&Name` structs returning a `Result` also get a `call_with_retry` method, which calls the
+/// function until it succeeds or the provided policy returns `false`. The policy is called with
+/// the number of failed attempts and the last error, and can wait between attempts.
 /// ```rust
 /// # use spread_macros::fn_struct;
-/// fn_struct!(
-///     // `T` must be listed here as one of the arguments use it.
-///     struct &VecPush<T: Clone>
-///     for fn Vec::<T>::push(
-///         &mut self,
-///         +value: T
-///     );
+/// use std::cell::Cell;
 ///
-///     // note that we can declare multiple structs in a single macro call by
-///     // separating them with `;`.
+/// fn flaky(failures: &Cell<u32>) -> Result<&'static str, String> {
+///     if failures.get() == 0 {
+///         return Ok("done");
+///     }
+///     failures.set(failures.get() - 1);
+///     Err("flaky".to_string())
+/// }
 ///
-///     struct &VecPop
-///     // `T` must be listed here as no arguments use it but it is still used by the function.
-///     for<T> fn Vec::<T>::pop(
-///         &mut self,
-///     ) -> Option<T>
+/// fn_struct!(
+///     struct &Flaky
+///     for fn flaky(&failures: Cell<u32>) -> Result<&'static str, String>
 /// );
 ///
-/// let mut list = vec![1, 2, 3, 4];
+/// let flaky = Flaky { failures: Cell::new(2) };
+/// let res = flaky.call_with_retry(|attempts, _err| {
+///     std::thread::sleep(std::time::Duration::from_millis(attempts.into()));
+///     attempts < 5
+/// });
+/// assert_eq!(res, Ok("done"));
 ///
-/// let pop = VecPop { };
-/// pop.call(&mut list);
-/// pop.call(&mut list);
-/// assert_eq!(&list, &[1, 2]);
+/// let flaky = Flaky { failures: Cell::new(10) };
+/// assert_eq!(flaky.call_with_retry(|attempts, _err| attempts < 3), Err("flaky".to_string()));
+/// ```
 ///
-/// let push = VecPush { value: 10 };
-/// push.call(&mut list);
-/// push.call(&mut list);
-/// assert_eq!(&list, &[1, 2, 10, 10]);
+/// Foreign functions can be targeted with `for unsafe extern "C" fn`, which makes the generated
+/// methods `unsafe`. ABI-only types like raw pointers can be produced from owned fields using a
+/// `[path] &field` modifier.
+/// ```rust
+/// # use spread_macros::fn_struct;
+/// use std::ffi::{c_char, CStr, CString};
+///
+/// extern "C" {
+///     fn strlen(s: *const c_char) -> usize;
+/// }
+///
+/// fn_struct!(
+///     struct &StrLen
+///     for unsafe extern "C" fn strlen(
+///         [CStr::as_ptr] &s: CString
+///     ) -> usize
+/// );
+///
+/// let str_len = StrLen { s: CString::new("hello").unwrap() };
+/// assert_eq!(unsafe { str_len.call() }, 5);
 /// ```
 ///
-/// Struct can be annotated with usual derives and attributes by writing them at the start.
+/// When the function returns a `Result<T, E>`, an additional `try_call` method is generated,
+/// which converts the error with `Into`. The target error type is inferred from the context, or
+/// can be fixed with `-> Result<T, E> map_err Target` to allow `?` propagation with error
+/// conversion.
 /// ```rust
 /// # use spread_macros::fn_struct;
+/// #[derive(Debug)]
+/// struct AppError(String);
+///
+/// impl From<std::num::ParseIntError> for AppError {
+///     fn from(err: std::num::ParseIntError) -> Self {
+///         AppError(err.to_string())
+///     }
+/// }
+///
+/// fn parse(input: &str) -> Result<u32, std::num::ParseIntError> {
+///     input.parse()
+/// }
+///
 /// fn_struct!(
-///     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-///     pub struct VecPush<T: Clone>
-///     for fn Vec::<T>::push(
-///         &mut self,
-///         value: T
-///     )
+///     struct Parse
+///     for fn parse(&input: String) -> Result<u32, std::num::ParseIntError>;
+///
+///     struct ParseApp
+///     for fn parse(&input: String) -> Result<u32, std::num::ParseIntError> map_err AppError
 /// );
+///
+/// let res: Result<u32, AppError> = Parse { input: "nope".to_string() }.try_call();
+/// assert!(res.is_err());
+///
+/// fn run(input: &str) -> Result<u32, AppError> {
+///     let value = ParseApp { input: input.to_string() }.try_call()?;
+///     Ok(value * 2)
+/// }
+///
+/// assert_eq!(run("21").unwrap(), 42);
+/// assert!(run("nope").is_err());
 /// ```
 #[proc_macro]
 pub fn fn_struct(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
     fn_struct::fn_struct(tokens)
 }
 
+/// Generates a [`fn_struct!`] struct for every public method of an inherent `impl` block, named
+/// after the type and the method (`Type::do_thing` gives `TypeDoThingArgs`). Methods can be opted
+/// out with `#[fn_struct_impl(skip)]`.
+///
+/// Arguments are stored as-is in the struct, in fields with the visibility of the method. Generic
+/// parameters of the `impl` block or the method are parameters of the struct if they are used by
+/// the arguments, or of `call` otherwise. All their bounds are checked by `call`.
+/// ```rust
+/// mod greeter {
+///     use spread_macros::fn_struct_impl;
+///
+///     pub struct Greeter {
+///         greeting: String,
+///     }
+///
+///     #[fn_struct_impl]
+///     impl Greeter {
+///         pub fn new(greeting: &str) -> Self {
+///             Self { greeting: greeting.to_string() }
+///         }
+///
+///         pub fn greet(&self, name: &str, excited: bool) -> String {
+///             let end = if excited { "!" } else { "." };
+///             format!("{}, {name}{end}", self.greeting)
+///         }
+///
+///         #[fn_struct_impl(skip)]
+///         pub fn greeting(&self) -> &str {
+///             &self.greeting
+///         }
+///     }
+/// }
+///
+/// use greeter::{GreeterGreetArgs, GreeterNewArgs};
+///
+/// let greeter = GreeterNewArgs { greeting: "Hello" }.call();
+/// let greet = GreeterGreetArgs { name: "World", excited: true };
+/// assert_eq!(greet.call(&greeter), "Hello, World!");
+/// ```
+#[proc_macro_attribute]
+pub fn fn_struct_impl(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    fn_struct_impl::fn_struct_impl(attr, item)
+}
+
 /// Asserts that some fields of the provided value match the expectation.
 ///
 /// This expectation can be expressed in 2 ways:
 /// - Another value can be provided, followed by a list of fields both values have in common
-///   and should be equal.
-/// - An anonymous struct with the same syntax as [`anon!`](crate::anon!).
+///   and should be equal. The list can also contain getters without arguments such as `len()`,
+///   which are compared by the value they return.
+/// - An anonymous struct with the same syntax as [`anon!`](crate::anon!). It can also contain
+///   `field => predicate` entries, where the field matches if the predicate returns `true` when
+///   called with a reference to it. Mismatching predicates are reported in the same failure
+///   message as the other fields.
+///
+/// Values of structs deriving [`CompareFields`] can also be compared on all their fields except
+/// some of them with `assert_fields_eq!(left, right, except [field1, field2])`.
+///
+/// Fields of both forms can be prefixed with `[approx(epsilon)]` to compare numbers such as floats
+/// within a tolerance instead of exactly.
 ///
 /// Afterward, the macro accepts a custom panic message with formating like [`assert_eq!`](core::assert_eq!).
 ///
-/// It uses the in-scope `assert_eq!` macro, which allows to use alternative macros like
-/// `similar_asserts::assert_eq!` if wanted.
+/// All fields are compared before failing, and the panic message lists every mismatching field with
+/// its left and right values. It uses the in-scope `assert_eq!` macro, which allows to use
+/// alternative macros like `similar_asserts::assert_eq!` if wanted.
 ///
 /// ```rust
 /// # use spread_macros::{anon, assert_fields_eq};
@@ -410,8 +2607,299 @@ pub fn fn_struct(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
 ///     [bar, baz],
 ///     "unexpected fields in {exemple:?}"
 /// );
+///
+/// let (list, other) = (vec![1, 2, 3], vec![3, 2, 1]);
+/// assert_fields_eq!(list, other, [len(), is_empty()]);
+///
+/// assert_fields_eq!(exemple, {
+///     bar => |bar| bar.starts_with("ex"),
+///     baz: true,
+/// });
+///
+/// #[derive(Debug)]
+/// struct Point {
+///     x: f64,
+///     y: f64,
+/// }
+///
+/// let (point, other) = (Point { x: 0.1 + 0.2, y: 1.0 }, Point { x: 0.3, y: 1.0 });
+/// assert_fields_eq!(point, other, [[approx(1e-9)] x, y]);
+/// assert_fields_eq!(point, { [approx(1e-9)] x: 0.3, y: 1.0 });
+///
+/// // Other brackets are custom modifiers, like in `anon!`
+/// #[derive(Debug)]
+/// struct Settings {
+///     limit: Option<u32>,
+/// }
+///
+/// assert_fields_eq!(Settings { limit: Some(10) }, { [Some] limit: 10 });
+/// ```
+///
+/// With the `regex` feature enabled, fields of the anonymous struct form can be written
+/// `[regex("pattern")] field` to assert that a string field matches a regular expression. `regex`
+/// must be a dependency of the calling crate.
+///
+#[cfg_attr(feature = "regex", doc = "```rust")]
+#[cfg_attr(not(feature = "regex"), doc = "```rust,ignore")]
+/// # use spread_macros::assert_fields_eq;
+/// #[derive(Debug)]
+/// struct User {
+///     id: String,
+///     name: String,
+/// }
+///
+/// let user = User {
+///     id: String::from("user_42"),
+///     name: String::from("bob"),
+/// };
+///
+/// assert_fields_eq!(user, {
+///     [regex("^user_[0-9]+$")] id,
+///     name: String::from("bob"),
+/// });
 /// ```
 #[proc_macro]
 pub fn assert_fields_eq(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
     assert_fields_eq::assert_fields_eq(tokens)
 }
+
+/// Asserts that some fields of the provided value differ from the expectation, which is the
+/// negative counterpart of [`assert_fields_eq!`] with the same syntaxes.
+///
+/// By default at least one of the listed fields must differ. With `all` before the list of fields
+/// or the anonymous struct, every listed field must differ.
+///
+/// It uses the in-scope `assert_ne!` macro.
+///
+/// ```rust
+/// # use spread_macros::assert_fields_ne;
+/// #[derive(Clone, Debug)]
+/// struct Counter {
+///     name: String,
+///     count: u32,
+///     updated: bool,
+/// }
+///
+/// let before = Counter {
+///     name: String::from("counter"),
+///     count: 1,
+///     updated: false,
+/// };
+///
+/// let mut after = before.clone();
+/// after.count += 1;
+/// after.updated = true;
+///
+/// assert_fields_ne!(after, before, [name, count]);
+/// assert_fields_ne!(after, before, all [count, updated], "{after:?} was not updated");
+/// assert_fields_ne!(after, all {
+///     count: 1,
+///     updated: false,
+/// });
+/// ```
+///
+/// ```rust,should_panic
+/// # use spread_macros::assert_fields_ne;
+/// # #[derive(Clone, Debug)]
+/// # struct Counter {
+/// #     name: String,
+/// #     count: u32,
+/// # }
+/// let before = Counter { name: String::from("counter"), count: 1 };
+/// let mut after = before.clone();
+/// after.count += 1;
+///
+/// // panics: `name` is equal in both values
+/// assert_fields_ne!(after, before, all [name, count]);
+/// ```
+#[proc_macro]
+pub fn assert_fields_ne(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    assert_fields_ne::assert_fields_ne(tokens)
+}
+
+/// Non-panicking form of [`assert_fields_eq!`] with the same syntaxes, which evaluates to a
+/// `Result<(), String>`.
+///
+/// The error lists every mismatching field with the `Debug` representation of both values,
+/// preceded by the custom message if one is provided. It allows to aggregate failures or to
+/// validate values outside of tests.
+///
+/// ```rust
+/// # use spread_macros::try_assert_fields_eq;
+/// #[derive(Debug)]
+/// struct Exemple {
+///     foo: u32,
+///     bar: String,
+///     baz: bool,
+/// }
+///
+/// let exemple = Exemple {
+///     foo: 42,
+///     bar: String::from("exemple"),
+///     baz: true,
+/// };
+///
+/// assert!(try_assert_fields_eq!(exemple, { foo: 42, baz: true }).is_ok());
+///
+/// let result = try_assert_fields_eq!(exemple, {
+///     foo: 42,
+///     bar: String::from("other"),
+///     baz: false,
+/// }, "unexpected {}", "exemple");
+///
+/// assert_eq!(
+///     result.unwrap_err(),
+///     "unexpected exemple:\n  `bar`: left: \"exemple\", right: \"other\"\n  `baz`: left: true, right: false"
+/// );
+/// ```
+#[proc_macro]
+pub fn try_assert_fields_eq(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    try_assert_fields_eq::try_assert_fields_eq(tokens)
+}
+
+/// Compares some fields of the provided value with the expectation, with the same syntaxes as
+/// [`assert_fields_eq!`] (without a custom message), and evaluates to a `bool`.
+///
+/// It can be used in conditions, match guards or iterator adapters where panicking is not
+/// appropriate.
+///
+/// ```rust
+/// # use spread_macros::fields_eq;
+/// struct User {
+///     name: &'static str,
+///     admin: bool,
+///     active: bool,
+/// }
+///
+/// let users = [
+///     User { name: "alice", admin: true, active: true },
+///     User { name: "bob", admin: false, active: true },
+///     User { name: "carol", admin: true, active: false },
+/// ];
+///
+/// let active_admins: Vec<_> = users
+///     .iter()
+///     .filter(|user| fields_eq!(user, { admin: true, active: true }))
+///     .map(|user| user.name)
+///     .collect();
+/// assert_eq!(active_admins, ["alice"]);
+///
+/// assert!(fields_eq!(users[0], users[2], [admin]));
+/// assert!(!fields_eq!(users[0], users[2], [admin, active]));
+/// ```
+#[proc_macro]
+pub fn fields_eq(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    fields_eq::fields_eq(tokens)
+}
+
+/// Asserts that some fields of the provided value match patterns, like a chain of
+/// [`matches!`](core::matches!) on each field.
+///
+/// Each pattern is matched against a reference to the field, such that patterns binding values
+/// don't move out of it. All fields are checked before panicking, and the failure message lists
+/// every field that doesn't match with its value.
+///
+/// Afterward, the macro accepts a custom panic message with formating like [`assert!`](core::assert!).
+///
+/// ```rust
+/// # use spread_macros::assert_fields_matches;
+/// #[derive(Debug)]
+/// enum Status {
+///     Ok,
+///     NotFound,
+///     Error(u16),
+/// }
+///
+/// #[derive(Debug)]
+/// struct Response {
+///     status: Status,
+///     body: Option<String>,
+///     error: Option<String>,
+///     retries: u32,
+/// }
+///
+/// let response = Response {
+///     status: Status::Ok,
+///     body: Some(String::from("hello")),
+///     error: None,
+///     retries: 1,
+/// };
+///
+/// assert_fields_matches!(response, {
+///     status: Status::Ok | Status::NotFound,
+///     body: Some(body) if body.starts_with("hel"),
+///     error: None,
+///     retries: 0..=2,
+/// });
+///
+/// let panic = std::panic::catch_unwind(|| {
+///     assert_fields_matches!(response, { status: Status::Error(_), error: Some(_) });
+/// });
+/// assert!(panic.is_err());
+/// ```
+#[proc_macro]
+pub fn assert_fields_matches(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    assert_fields_matches::assert_fields_matches(tokens)
+}
+
+/// Allows to compare values of this struct with the `except [fields]` form of
+/// [`assert_fields_eq!`], [`assert_fields_ne!`], [`try_assert_fields_eq!`] and [`fields_eq!`],
+/// which compares all fields except the listed ones. Fields of tuple structs are listed by index.
+///
+/// All fields must implement `PartialEq` and `Debug` to use these macros, even the excepted ones.
+/// Fields which can't be compared, such as handles or closures, can be marked with
+/// `#[compare_fields(skip)]` to never be compared.
+///
+/// ```rust
+/// use spread_macros::{assert_fields_eq, fields_eq, CompareFields};
+///
+/// #[derive(CompareFields)]
+/// struct Post {
+///     id: u64,
+///     title: String,
+///     published: bool,
+///     updated_at: u64,
+/// }
+///
+/// let post = Post {
+///     id: 1,
+///     title: String::from("Hello"),
+///     published: true,
+///     updated_at: 1700000000,
+/// };
+///
+/// let copy = Post {
+///     id: 2,
+///     title: post.title.clone(),
+///     published: true,
+///     updated_at: 1700000042,
+/// };
+///
+/// assert_fields_eq!(post, copy, except [id, updated_at]);
+/// assert!(!fields_eq!(post, copy, except [id]));
+/// ```
+///
+/// The struct can be defined in another module than the assertions.
+///
+/// ```rust
+/// use spread_macros::assert_fields_eq;
+///
+/// mod model {
+///     #[derive(spread_macros::CompareFields)]
+///     pub struct User {
+///         pub id: u64,
+///         pub name: String,
+///         #[compare_fields(skip)]
+///         pub on_login: Box<dyn Fn(&str)>,
+///     }
+/// }
+///
+/// let name = String::from("Alice");
+/// let alice = model::User { id: 1, name: name.clone(), on_login: Box::new(|_| ()) };
+/// let other = model::User { id: 2, name, on_login: Box::new(|name| println!("{name}")) };
+/// assert_fields_eq!(alice, other, except [id]);
+/// ```
+#[proc_macro_derive(CompareFields, attributes(compare_fields))]
+pub fn compare_fields(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    compare_fields::compare_fields(tokens)
+}
@@ -0,0 +1,51 @@
+use {super::*, slet::SLet};
+
+pub fn sasync(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let SAsync { captures, body } = parse_macro_input!(tokens as SAsync);
+
+    let captures = captures.expand();
+
+    quote! {
+        {
+            #captures
+            #body
+        }
+    }
+    .into()
+}
+
+struct SAsync {
+    captures: SLet,
+    body: TokenStream,
+}
+
+impl Parse for SAsync {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let bracketed;
+        syn::bracketed!(bracketed in input);
+        let captures = bracketed.parse()?;
+
+        // `{ .. }` is shorthand for `async move { .. }`
+        let body = if input.peek(Token![async]) {
+            let body: syn::ExprAsync = input.parse()?;
+
+            if body.capture.is_none() {
+                return Err(syn::Error::new(
+                    body.async_token.span(),
+                    "async block must be `move` to capture the bindings",
+                ));
+            }
+
+            quote! { #body }
+        } else {
+            let block: syn::Block = input.parse()?;
+            quote! { async move #block }
+        };
+
+        if !input.is_empty() {
+            return Err(input.error("unexpected tokens after async block"));
+        }
+
+        Ok(Self { captures, body })
+    }
+}
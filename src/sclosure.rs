@@ -0,0 +1,39 @@
+use {super::*, slet::SLet};
+
+pub fn sclosure(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let SClosure { captures, closure } = parse_macro_input!(tokens as SClosure);
+
+    let captures = captures.expand();
+
+    quote! {
+        {
+            #captures
+            #closure
+        }
+    }
+    .into()
+}
+
+struct SClosure {
+    captures: SLet,
+    closure: syn::ExprClosure,
+}
+
+impl Parse for SClosure {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let bracketed;
+        syn::bracketed!(bracketed in input);
+        let captures = bracketed.parse()?;
+
+        let closure: syn::ExprClosure = input.parse()?;
+
+        if closure.capture.is_none() {
+            return Err(syn::Error::new(
+                closure.or1_token.span(),
+                "closure must be `move` to capture the bindings",
+            ));
+        }
+
+        Ok(Self { captures, closure })
+    }
+}
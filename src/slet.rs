@@ -1,23 +1,102 @@
 use super::{common::*, *};
 
 pub fn slet(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let SLet { items } = parse_macro_input!(tokens as SLet);
+    let slet = parse_macro_input!(tokens as SLet);
+    slet.expand().into()
+}
+
+pub struct SLet {
+    // Outer attributes of each item, emitted on its `let` statement
+    items_attrs: Vec<Vec<syn::Attribute>>,
+    items: Punctuated<SLetItem, Token![,]>,
+}
+
+impl SLet {
+    pub fn expand(&self) -> TokenStream {
+        let let_expansions = self
+            .items
+            .iter()
+            .zip(&self.items_attrs)
+            .map(|(item, attrs)| {
+                let expansion = match item {
+                    SLetItem::Spread(item) => item.let_expansion(),
+                    SLetItem::Try(token_try, field) => field.let_expansion(Some(token_try)),
+                    SLetItem::LetElse { pat, expr, diverge } => match diverge {
+                        syn::Expr::Block(_) => quote! { let #pat = #expr else #diverge; },
+                        _ => quote! { let #pat = #expr else { #diverge }; },
+                    },
+                };
 
-    let let_expansions = items.iter().map(SpreadItem::let_expansion);
+                quote! { #( #attrs )* #expansion }
+            });
 
-    quote! {
-        #( #let_expansions )*
+        quote! {
+            #( #let_expansions )*
+        }
     }
-    .into()
 }
 
-struct SLet {
-    items: Punctuated<SpreadItem, Token![,]>,
+enum SLetItem {
+    Spread(SpreadItem),
+    // `try name: value`, the value is propagated with `?` before other modifiers
+    Try(Token![try], Field),
+    // `pattern =? expr else diverge`, expanded to a `let .. else` binding
+    LetElse {
+        pat: syn::Pat,
+        expr: syn::Expr,
+        diverge: syn::Expr,
+    },
+}
+
+impl Parse for SLetItem {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![try]) {
+            let token_try: Token![try] = input.parse()?;
+            let field =
+                match input.parse()? {
+                    SpreadItem::Field(field) if field.value.is_some() => field,
+                    _ => return Err(syn::Error::new(
+                        token_try.span(),
+                        "`try` must be followed by a field with a value, as in `try name: value`",
+                    )),
+                };
+
+            return Ok(SLetItem::Try(token_try, field));
+        }
+
+        let fork = input.fork();
+        let is_let_else = fork.call(syn::Pat::parse_single).is_ok()
+            && fork.peek(Token![=])
+            && !fork.peek(Token![==])
+            && fork.peek2(Token![?]);
+
+        if !is_let_else {
+            return input.parse().map(SLetItem::Spread);
+        }
+
+        let pat = input.call(syn::Pat::parse_single)?;
+        let _: Token![=] = input.parse()?;
+        let _: Token![?] = input.parse()?;
+        let expr = input.call(syn::Expr::parse_without_eager_brace)?;
+        let _: Token![else] = input.parse()?;
+        let diverge = input.parse()?;
+
+        Ok(SLetItem::LetElse { pat, expr, diverge })
+    }
 }
 
 impl Parse for SLet {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let items = Punctuated::<SpreadItem, Token![,]>::parse_terminated(input)?;
+        let mut items = Punctuated::<SLetItem, Token![,]>::new();
+        let mut items_attrs = vec![];
+        while !input.is_empty() {
+            items_attrs.push(input.call(syn::Attribute::parse_outer)?);
+            items.push_value(input.parse()?);
+            if input.is_empty() {
+                break;
+            }
+            items.push_punct(input.parse()?);
+        }
 
         // Forbid empty struct
         if items.is_empty() {
@@ -27,9 +106,9 @@ impl Parse for SLet {
             ));
         }
 
-        // No `..remaining` or `field: value`
+        // No `..remaining`
         for item in items.iter() {
-            if let SpreadItem::FinalSpread(dotdot, _) = item {
+            if let SLetItem::Spread(SpreadItem::FinalSpread(dotdot, _, _)) = item {
                 return Err(syn::Error::new(
                     dotdot.span(),
                     "`..remaining` is not allowed in this macro",
@@ -37,6 +116,37 @@ impl Parse for SLet {
             }
         }
 
-        Ok(Self { items })
+        // No `name ?: value` nor `?name: value`, errors are propagated with `try name: value`
+        for item in items.iter() {
+            let (SLetItem::Spread(SpreadItem::Field(field)) | SLetItem::Try(_, field)) = item
+            else {
+                continue;
+            };
+
+            if let Some(token_optional) = &field.optional {
+                return Err(syn::Error::new(
+                    token_optional.span(),
+                    "`name ?: value` is not allowed in this macro, use `try name: value` to \
+                    propagate errors with `?`",
+                ));
+            }
+
+            if let (
+                Some(_),
+                Some(
+                    SpreadModifier::UnwrapOrDefault(token_unwrap)
+                    | SpreadModifier::CloneUnwrapOrDefault(_, token_unwrap),
+                ),
+            ) = (&field.value, &field.modifier)
+            {
+                return Err(syn::Error::new(
+                    token_unwrap.span(),
+                    "`?name: value` is ambiguous in this macro, use `try name: value` to \
+                    propagate errors with `?`, or call `unwrap_or_default` on the value",
+                ));
+            }
+        }
+
+        Ok(Self { items_attrs, items })
     }
 }
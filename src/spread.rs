@@ -1,43 +1,202 @@
 use super::{common::*, *};
 
 pub fn spread(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let Spread { struct_name, items } = parse_macro_input!(tokens as Spread);
+    let Spread {
+        trace,
+        target,
+        items,
+    } = parse_macro_input!(tokens as Spread);
 
-    let let_sources = items.iter().filter_map(|item| match item {
-        SpreadItem::SpreadList(SpreadList {
-            source,
-            source_ident,
-            ..
-        }) => Some(quote! { let #source_ident = #source; }),
-        _ => None,
-    });
+    let mut lets: Vec<_> = items
+        .iter()
+        .filter_map(|item| match item {
+            SpreadItem::SpreadList(spread_list) => Some(spread_list.source_binding()),
+            _ => None,
+        })
+        .collect();
 
-    let fields_expansions = items.iter().map(SpreadItem::field_expansion);
+    let value = match target {
+        SpreadTarget::Struct(struct_name) => {
+            let has_optional = items.iter().any(|item| {
+                matches!(
+                    item,
+                    SpreadItem::Field(Field {
+                        optional: Some(_),
+                        ..
+                    })
+                )
+            });
+
+            // `..remaining` is bound to a variable as it is used by `field ?: value` fields. Field
+            // values are evaluated first into temporaries, keeping the evaluation order of struct
+            // literals.
+            let base = syn::Ident::new("__base", Span::call_site());
+            let mut fields_expansions = vec![];
+            if has_optional {
+                for item in items.iter() {
+                    match item {
+                        SpreadItem::Field(field) if field.optional.is_some() => {
+                            let temp = temp_ident(fields_expansions.len());
+                            let (binding, expansion) = field.optional_field_expansion(&temp, &base);
+                            lets.push(binding);
+                            fields_expansions.push(expansion);
+                        }
+                        SpreadItem::FinalSpread(_, _, _) => (),
+                        _ => {
+                            for (name, value) in item.values_expansion() {
+                                let temp = temp_ident(fields_expansions.len());
+                                lets.push(quote! { let #temp = #value; });
+                                fields_expansions.push(quote! { #name: #temp });
+                            }
+                        }
+                    }
+                }
+            }
+
+            for item in items.iter() {
+                match item {
+                    SpreadItem::FinalSpread(dotdot, None, source) if has_optional => {
+                        lets.push(quote! { let #base = #source; });
+                        fields_expansions.push(quote! { #dotdot #base });
+                    }
+                    // Type must be known before fields are accessed
+                    SpreadItem::FinalSpread(dotdot, Some(token_into), source) if has_optional => {
+                        let into = quote_spanned!(token_into.span()=> ::core::convert::Into::into);
+                        lets.push(quote! { let #base: #struct_name = #into ( #source ); });
+                        fields_expansions.push(quote! { #dotdot #base });
+                    }
+                    _ if has_optional => (),
+                    _ => fields_expansions.push(item.field_expansion()),
+                }
+            }
+
+            quote! {
+                #struct_name {
+                    #( #fields_expansions ),*
+                }
+            }
+        }
+        SpreadTarget::Builder { builder, tail } => {
+            let setters_expansions = items
+                .iter()
+                .flat_map(SpreadItem::values_expansion)
+                .map(|(name, value)| quote! { . #name ( #value ) });
+
+            quote! {
+                ( #builder ) #( #setters_expansions )* #tail
+            }
+        }
+        SpreadTarget::Fn(fn_path) => {
+            let args_expansions = items
+                .iter()
+                .flat_map(SpreadItem::values_expansion)
+                .map(|(_, value)| value);
+
+            quote! {
+                #fn_path ( #( #args_expansions ),* )
+            }
+        }
+    };
+
+    if let Some(trace) = trace {
+        let trace = if cfg!(feature = "tracing") {
+            quote_spanned!(trace.span()=> ::tracing::trace!)
+        } else {
+            quote_spanned!(trace.span()=> ::log::trace!)
+        };
+
+        return quote! {
+            {
+                #( #lets )*
+
+                let __value = #value;
+                #trace("spread!: {:?}", __value);
+                __value
+            }
+        }
+        .into();
+    }
+
+    // Without bindings a plain expression is emitted, which can be used in const contexts.
+    if lets.is_empty() {
+        return value.into();
+    }
 
     quote! {
         {
-            #( #let_sources )*
+            #( #lets )*
 
-            #struct_name {
-                #( #fields_expansions ),*
-            }
+            #value
         }
     }
     .into()
 }
 
 struct Spread {
-    struct_name: syn::Ident,
+    // Some = `#![trace]`, constructed value is logged
+    trace: Option<syn::Attribute>,
+    target: SpreadTarget,
     items: Punctuated<SpreadItem, Token![,]>,
 }
 
+enum SpreadTarget {
+    // `Name { .. }`
+    Struct(syn::Ident),
+    // `builder expr { .. } tail`, fields are passed to setters of the same name
+    Builder {
+        builder: syn::Expr,
+        tail: TokenStream,
+    },
+    // `fn path { .. }`, fields are passed as arguments in order
+    Fn(syn::ExprPath),
+}
+
 impl Parse for Spread {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let struct_name = input.parse()?;
+        let is_builder = input.peek(syn::Ident)
+            && !input.peek2(Brace)
+            && input
+                .fork()
+                .parse::<syn::Ident>()
+                .is_ok_and(|ident| ident == "builder");
+
+        let mut target = if is_builder {
+            let _: syn::Ident = input.parse()?;
+            SpreadTarget::Builder {
+                builder: input.call(syn::Expr::parse_without_eager_brace)?,
+                tail: TokenStream::new(),
+            }
+        } else if input.peek(Token![fn]) {
+            let _: Token![fn] = input.parse()?;
+            SpreadTarget::Fn(input.parse()?)
+        } else {
+            SpreadTarget::Struct(input.parse()?)
+        };
 
         let braced;
         let braces = braced!(braced in input);
 
+        let mut trace = None;
+        for attr in braced.call(syn::Attribute::parse_inner)? {
+            if !attr.path().is_ident("trace") {
+                return Err(syn::Error::new(
+                    attr.span(),
+                    "only `#![trace]` is allowed in this macro",
+                ));
+            }
+
+            attr.meta.require_path_only()?;
+
+            if !cfg!(any(feature = "tracing", feature = "log")) {
+                return Err(syn::Error::new(
+                    attr.span(),
+                    "`#![trace]` requires the `tracing` or `log` feature of `spread_macros`",
+                ));
+            }
+
+            trace = Some(attr);
+        }
+
         let mut items = Punctuated::<SpreadItem, Token![,]>::parse_terminated(&braced)?;
 
         // Forbid empty struct
@@ -50,7 +209,7 @@ impl Parse for Spread {
 
         // Only allow FinalSpread as last item
         for item in items.iter().rev().skip(1) {
-            if let SpreadItem::FinalSpread(dotdot, _) = item {
+            if let SpreadItem::FinalSpread(dotdot, _, _) = item {
                 return Err(syn::Error::new(
                     dotdot.span(),
                     "`..remaining` can only be used as the last item",
@@ -59,7 +218,7 @@ impl Parse for Spread {
         }
 
         // Cannot have trailing comma after FinalSpread
-        if let Some(SpreadItem::FinalSpread(_, _)) = items.last() {
+        if let Some(SpreadItem::FinalSpread(_, _, _)) = items.last() {
             if let Some(trailing) = items.pop_punct() {
                 return Err(syn::Error::new(
                     trailing.span(),
@@ -94,6 +253,48 @@ impl Parse for Spread {
             }
         }
 
-        Ok(Self { struct_name, items })
+        // `field ?: value` needs `..remaining` to fall back to
+        if !matches!(items.last(), Some(SpreadItem::FinalSpread(_, _, _)))
+            || !matches!(target, SpreadTarget::Struct(_))
+        {
+            for item in items.iter() {
+                if let SpreadItem::Field(Field {
+                    optional: Some(token_optional),
+                    ..
+                }) = item
+                {
+                    return Err(syn::Error::new(
+                        token_optional.span(),
+                        "`field ?: value` requires a struct with `..remaining`",
+                    ));
+                }
+            }
+        }
+
+        // Only struct literals support `..remaining`
+        if !matches!(target, SpreadTarget::Struct(_)) {
+            if let Some(SpreadItem::FinalSpread(dotdot, _, _)) = items.last() {
+                return Err(syn::Error::new(
+                    dotdot.span(),
+                    "`..remaining` is only allowed when creating a struct",
+                ));
+            }
+        }
+
+        // Builder is followed by any method calls to finish building
+        if let SpreadTarget::Builder { tail, .. } = &mut target {
+            *tail = input.parse()?;
+        }
+
+        Ok(Self {
+            trace,
+            target,
+            items,
+        })
     }
 }
+
+// Temporary holding the value of the field at `index` before `..remaining` is evaluated
+fn temp_ident(index: usize) -> syn::Ident {
+    syn::Ident::new(&format!("__field_{index}"), Span::call_site())
+}
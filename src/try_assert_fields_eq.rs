@@ -0,0 +1,42 @@
+use crate::{
+    assert_fields_eq::{expand_mismatches, AssertFieldsEq},
+    *,
+};
+
+pub fn try_assert_fields_eq(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let try_assert_fields_eq = parse_macro_input!(tokens as AssertFieldsEq);
+
+    if let Some(all) = &try_assert_fields_eq.all {
+        return syn::Error::new(all.span(), "`all` is only allowed in `assert_fields_ne!`")
+            .into_compile_error()
+            .into();
+    }
+
+    // Custom messages are followed by the mismatches
+    let message = try_assert_fields_eq.expand_message();
+    let mismatches = match try_assert_fields_eq.expand_except() {
+        Some(mismatches) => mismatches,
+        None => {
+            let (fields, bindings) = try_assert_fields_eq.expand_bindings();
+            let mismatches = expand_mismatches(&fields);
+            quote! {
+                #bindings
+
+                #mismatches
+            }
+        }
+    };
+
+    quote! {
+        {
+            #mismatches
+
+            if mismatches.is_empty() {
+                ::core::result::Result::<(), ::std::string::String>::Ok(())
+            } else {
+                ::core::result::Result::Err(::std::format!("{}:\n{}", #message, mismatches.join("\n")))
+            }
+        }
+    }
+    .into()
+}